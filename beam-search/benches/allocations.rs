@@ -0,0 +1,72 @@
+// 探索中のヒープ確保回数を数える
+// cargo bench -p beam-search --bench allocations
+use beam_search::beam_search::*;
+use beam_search::chokudai_search::*;
+use beam_search::generator::*;
+use beam_search::pool::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn count<F: FnOnce()>(f: F) -> (usize, usize) {
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = BYTES.load(Ordering::Relaxed);
+    f();
+    (
+        ALLOCATIONS.load(Ordering::Relaxed) - allocations,
+        BYTES.load(Ordering::Relaxed) - bytes,
+    )
+}
+
+fn main() {
+    // pool off は StatePool で State を使い回さない場合、on は探索の既定の容量 (pool::beam_capacity)
+    println!("search,pool,size,allocations,bytes");
+    for size in [4, 8, 16, 32] {
+        let state = generate(size, size, size * 2, 0);
+        for pool in ["off", "on"] {
+            let capacity = |beam_width| match pool {
+                "off" => 0,
+                _ => beam_capacity(&state, beam_width),
+            };
+            let (allocations, bytes) = count(|| {
+                beam_search_with_pool_capacity(&state, 100, size * 2, capacity(100));
+            });
+            println!("beam_search,{},{},{},{}", pool, size, allocations, bytes);
+
+            let (allocations, bytes) = count(|| {
+                ChokudaiSearch::new(&state, 10, size * 2)
+                    .with_pool_capacity(capacity(10))
+                    .run(10);
+            });
+            println!(
+                "chokudai_search,{},{},{},{}",
+                pool, size, allocations, bytes
+            );
+        }
+    }
+}
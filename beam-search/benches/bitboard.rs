@@ -0,0 +1,36 @@
+// State と BitState で同じ beam_search を比べる
+// cargo bench -p beam-search --bench bitboard
+use beam_search::beam_search::*;
+use beam_search::bitboard::*;
+use beam_search::generator::*;
+use beam_search::state::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+fn bench_beam_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("beam_search");
+    let example = State::new(
+        Pos::new(1, 1),
+        vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]],
+    );
+    let instances = [
+        ("3x4", example),
+        ("6x6", generate(6, 6, 12, 0)),
+        ("10x10", generate(10, 10, 20, 0)),
+    ];
+    for (name, state) in instances {
+        let bit_state = BitState::from_state(&state).unwrap();
+        let beam_depth = state.end_turn;
+        group.bench_with_input(BenchmarkId::new("State", name), &state, |b, state| {
+            b.iter(|| beam_search(black_box(state), 100, beam_depth))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("BitState", name),
+            &bit_state,
+            |b, bit_state| b.iter(|| beam_search(black_box(bit_state), 100, beam_depth)),
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_beam_search);
+criterion_main!(benches);
@@ -0,0 +1,36 @@
+// 生成した迷路の大きさを変えながら各探索の実行時間を測る
+// cargo bench -p beam-search --bench search
+use beam_search::beam_search::*;
+use beam_search::chokudai_search::*;
+use beam_search::generator::*;
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const SIZES: [usize; 4] = [4, 8, 16, 32];
+
+fn bench_search(c: &mut Criterion) {
+    let mut group = c.benchmark_group("search");
+    group.sample_size(10);
+    for size in SIZES {
+        let state = generate(size, size, size * 2, 0);
+        let beam_depth = size * 2;
+        group.bench_with_input(BenchmarkId::new("beam_search", size), &state, |b, state| {
+            b.iter(|| beam_search(black_box(state), 100, beam_depth))
+        });
+        group.bench_with_input(
+            BenchmarkId::new("chokudai_search", size),
+            &state,
+            |b, state| b.iter(|| chokudai_search(black_box(state), 10, beam_depth, 10)),
+        );
+        group.bench_with_input(
+            BenchmarkId::new("chokudai_search_with_refcell", size),
+            &state,
+            |b, state| {
+                b.iter(|| chokudai_search_with_refcell(black_box(state), 10, beam_depth, 10))
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_search);
+criterion_main!(benches);
@@ -0,0 +1,81 @@
+// 迷路での1手。整数との相互変換は Right=0, Down=1, Left=2, Up=3, Stay=4, Custom(i)=5+i
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Action {
+    Right,
+    Down,
+    Left,
+    Up,
+    Stay,
+    // 移動量は State のルール (Rules::custom_moves[i]) で決まる
+    Custom(usize),
+}
+
+impl Action {
+    pub const MOVES: [Action; 4] = [Action::Right, Action::Down, Action::Left, Action::Up];
+
+    // (dy, dx)。Custom はルール次第なので None
+    pub fn delta(self) -> Option<(isize, isize)> {
+        match self {
+            Action::Right => Some((0, 1)),
+            Action::Down => Some((1, 0)),
+            Action::Left => Some((0, -1)),
+            Action::Up => Some((-1, 0)),
+            Action::Stay => Some((0, 0)),
+            Action::Custom(_) => None,
+        }
+    }
+
+    // 一方通行マスの表示に使う
+    pub fn arrow(self) -> Option<char> {
+        match self {
+            Action::Right => Some('>'),
+            Action::Down => Some('v'),
+            Action::Left => Some('<'),
+            Action::Up => Some('^'),
+            _ => None,
+        }
+    }
+}
+
+impl From<usize> for Action {
+    fn from(value: usize) -> Self {
+        match value {
+            0 => Action::Right,
+            1 => Action::Down,
+            2 => Action::Left,
+            3 => Action::Up,
+            4 => Action::Stay,
+            i => Action::Custom(i - 5),
+        }
+    }
+}
+
+impl From<Action> for usize {
+    fn from(action: Action) -> Self {
+        match action {
+            Action::Right => 0,
+            Action::Down => 1,
+            Action::Left => 2,
+            Action::Up => 3,
+            Action::Stay => 4,
+            Action::Custom(i) => 5 + i,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_integer_encoding() {
+        for i in 0..10 {
+            assert_eq!(usize::from(Action::from(i)), i);
+        }
+        assert_eq!(Action::from(1), Action::Down);
+        assert_eq!(Action::from(4), Action::Stay);
+        assert_eq!(Action::from(7), Action::Custom(2));
+        assert_eq!(usize::from(Action::Up), 3);
+    }
+}
@@ -0,0 +1,105 @@
+use crate::action::*;
+use crate::beam_search::*;
+use crate::observer::*;
+use crate::state::*;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnytimeResult {
+    pub action: Option<Action>,
+    pub score: usize,
+    // 最良解を見つけたときのビーム幅
+    pub beam_width: usize,
+    // 最後まで探索し終えた回数
+    pub iterations: usize,
+}
+
+// ビーム幅を 1, 2, 4, ... と倍にしながら time_limit まで beam_search を繰り返す
+// 幅1の探索は制限時間に関係なく必ず最後まで回すので、すぐに何かしらの答えが得られる
+// 前回の最良の計画を warm start として渡すので、幅を広げた探索が前回より悪くなることはない
+pub fn anytime_beam_search(
+    state: &State,
+    beam_depth: usize,
+    time_limit: Duration,
+) -> AnytimeResult {
+    let deadline = Instant::now() + time_limit;
+    let mut result = AnytimeResult {
+        action: None,
+        score: 0,
+        beam_width: 0,
+        iterations: 0,
+    };
+
+    let mut beam_width = 1;
+    let mut plan = vec![];
+    loop {
+        // どの層でも候補数がビーム幅以下なら枝刈りが起きていない = 全探索できている
+        let mut pruned = false;
+        let mut observer = |info: &LayerInfo| {
+            if info.depth < beam_depth && info.beam_size > beam_width {
+                pruned = true;
+            }
+        };
+        let limit = if result.iterations == 0 {
+            None
+        } else {
+            Some(deadline)
+        };
+        let Some(best) = beam_search_until_with_warm_start(
+            state,
+            beam_width,
+            beam_depth,
+            limit,
+            &plan,
+            &mut observer,
+        ) else {
+            break;
+        };
+
+        result.iterations += 1;
+        if result.action.is_none() || best.evaluated_score >= result.score {
+            result.action = best.first_action;
+            result.score = best.evaluated_score;
+            result.beam_width = beam_width;
+            plan = best.actions[state.actions.len()..].to_vec();
+        }
+
+        // これ以上幅を広げても同じ結果になる
+        if !pruned || Instant::now() >= deadline {
+            break;
+        }
+        beam_width *= 2;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::*;
+
+    #[test]
+    fn test_anytime_beam_search() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let result = anytime_beam_search(&state, 4, Duration::from_secs(10));
+
+        // 幅を広げていけば全探索と同じ答えになる (beam_search の w4_d4 と同じ)
+        assert_eq!(result.action, Some(Action::Right));
+        assert_eq!(result.score, 20);
+        assert!(result.iterations >= 2);
+    }
+
+    #[test]
+    fn test_anytime_beam_search_without_time() {
+        // 制限時間が0でも幅1の結果は返る
+        let state = generate(10, 10, 20, 3);
+        let result = anytime_beam_search(&state, 20, Duration::ZERO);
+
+        assert!(result.action.is_some());
+        assert_eq!(result.beam_width, 1);
+        assert_eq!(result.iterations, 1);
+    }
+}
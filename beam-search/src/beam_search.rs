@@ -1,47 +1,340 @@
 #![allow(unused)]
+use crate::action::*;
+use crate::dominance::*;
+use crate::observer::*;
+use crate::pool::*;
+use crate::search_state::*;
 use crate::state::*;
 // Ref. ゲームで学ぶ探索アルゴリズム実践入門
 
 use std::collections::BinaryHeap;
+use std::collections::HashSet;
+use std::time::Instant;
 
-fn beam_search(state: &State, beam_width: usize, beam_depth: usize) -> Option<usize> {
+// State 以外にも SearchState を実装した状態 (bitboard::BitState など) で探索できる
+pub fn beam_search<S: SearchState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+) -> Option<Action> {
+    beam_search_with_observer(state, beam_width, beam_depth, &mut NoopObserver)
+}
+
+pub fn beam_search_with_observer<S: SearchState, O: SearchObserver<S>>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    observer: &mut O,
+) -> Option<Action> {
+    beam_search_until(state, beam_width, beam_depth, None, observer)
+        .and_then(|state| state.first_action())
+}
+
+// 最良の状態に至る行動列を返す
+// warm_start に前回の計画の残りを渡すと、それをたどった状態を各層に混ぜるので
+// 前回の計画より悪い計画は返さない
+pub fn beam_search_plan(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    warm_start: &[Action],
+) -> Vec<Action> {
+    match search(
+        state,
+        beam_width,
+        beam_depth,
+        None,
+        warm_start,
+        &NoDominance,
+        &mut NoopObserver,
+    ) {
+        Some(layer) => layer[0].actions[state.actions.len()..].to_vec(),
+        None => vec![],
+    }
+}
+
+// 探索の開始からの行動列と、それでたどり着いた状態
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Plan {
+    pub actions: Vec<Action>,
+    pub state: State,
+}
+
+impl Plan {
+    pub fn score(&self) -> usize {
+        self.state.evaluated_score
+    }
+}
+
+// 最後の層から、盤面が重複しないように良い順に k 個の計画を返す
+pub fn beam_search_top_k(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    k: usize,
+) -> Vec<Plan> {
+    match search(
+        state,
+        beam_width,
+        beam_depth,
+        None,
+        &[],
+        &NoDominance,
+        &mut NoopObserver,
+    ) {
+        Some(layer) => top_k_plans(state, layer, k),
+        None => vec![],
+    }
+}
+
+// states は良い順に並んでいること。同じ盤面 (hash_key) なら先に来た方を残す
+pub(crate) fn top_k_plans(
+    root: &State,
+    states: impl IntoIterator<Item = State>,
+    k: usize,
+) -> Vec<Plan> {
+    let mut seen = HashSet::new();
+    states
+        .into_iter()
+        .filter(|state| seen.insert(state.hash_key()))
+        .take(k)
+        .map(|state| Plan {
+            actions: state.actions[root.actions.len()..].to_vec(),
+            state,
+        })
+        .collect()
+}
+
+// deadline までに探索し終えたら最良の状態を返す。間に合わなければ None
+// deadline が None なら時間無制限
+pub fn beam_search_until<S: SearchState, O: SearchObserver<S>>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    deadline: Option<Instant>,
+    observer: &mut O,
+) -> Option<S> {
+    search(
+        state,
+        beam_width,
+        beam_depth,
+        deadline,
+        &[],
+        &NoDominance,
+        observer,
+    )
+    .map(|layer| layer.into_iter().next().unwrap())
+}
+
+// warm_start を渡せる beam_search_until。beam_search_plan と同じく計画をたどった状態は必ず残る
+pub fn beam_search_until_with_warm_start<S: SearchState, O: SearchObserver<S>>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    deadline: Option<Instant>,
+    warm_start: &[Action],
+    observer: &mut O,
+) -> Option<S> {
+    search(
+        state,
+        beam_width,
+        beam_depth,
+        deadline,
+        warm_start,
+        &NoDominance,
+        observer,
+    )
+    .map(|layer| layer.into_iter().next().unwrap())
+}
+
+// 各層で他の状態に支配される状態を捨ててからビーム幅で切る。最良の状態を返す
+pub fn beam_search_with_dominance<S: SearchState, D: Dominance<S>, O: SearchObserver<S>>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    dominance: &D,
+    observer: &mut O,
+) -> Option<S> {
+    search(
+        state,
+        beam_width,
+        beam_depth,
+        None,
+        &[],
+        dominance,
+        observer,
+    )
+    .map(|layer| layer.into_iter().next().unwrap())
+}
+
+// StatePool::new(pool_capacity) を使う beam_search
+pub fn beam_search_with_pool_capacity<S: SearchState>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    pool_capacity: usize,
+) -> Option<Action> {
+    search_with_pool(
+        state,
+        beam_width,
+        beam_depth,
+        None,
+        &[],
+        &NoDominance,
+        &mut NoopObserver,
+        StatePool::new(pool_capacity),
+    )
+    .and_then(|layer| layer[0].first_action())
+}
+
+fn search<S: SearchState, D: Dominance<S>, O: SearchObserver<S>>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    deadline: Option<Instant>,
+    warm_start: &[Action],
+    dominance: &D,
+    observer: &mut O,
+) -> Option<Vec<S>> {
+    search_with_pool(
+        state,
+        beam_width,
+        beam_depth,
+        deadline,
+        warm_start,
+        dominance,
+        observer,
+        StatePool::new(beam_capacity(state, beam_width)),
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn search_with_pool<S: SearchState, D: Dominance<S>, O: SearchObserver<S>>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    deadline: Option<Instant>,
+    warm_start: &[Action],
+    dominance: &D,
+    observer: &mut O,
+    // 展開し終えた状態や溢れた状態は pool に返して、子の複製に使い回す
+    mut pool: StatePool<S>,
+) -> Option<Vec<S>> {
     let mut now_beam = BinaryHeap::new();
     let initial_state = state.clone();
-    let mut best_state = initial_state.clone();
+    let mut seed_state = Some(initial_state.clone());
+    // 途中で1手も進めなくなった状態。exhaustive_search と同じく、最後の層と一緒に候補にする
+    let mut dead_ends = vec![];
 
     now_beam.push(initial_state);
     for t in 0..beam_depth {
+        let start = Instant::now();
         let mut next_beam = BinaryHeap::new();
+        let mut expanded = 0;
+        let mut seen = HashSet::new();
+        let mut duplicate_children = 0;
+        let mut scores = vec![];
+        let mut expanded_states = vec![];
         for i in 0..beam_width {
             if now_beam.is_empty() {
                 break;
             }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                return None;
+            }
             let now_state = now_beam.pop().unwrap();
+            expanded += 1;
             let legal_actions = now_state.legal_actions();
+            if legal_actions.is_empty() && t > 0 {
+                dead_ends.push(now_state);
+                continue;
+            }
             for action in legal_actions {
-                let mut next_state = now_state.clone();
+                let mut next_state = pool.clone_state(&now_state);
                 next_state.advance(action);
                 next_state.evaluate_score();
                 if t == 0 {
-                    next_state.first_action = Some(action);
+                    next_state.set_first_action(action);
+                }
+                if observer.detailed() {
+                    if !seen.insert(next_state.hash_key()) {
+                        duplicate_children += 1;
+                    }
+                    scores.push(next_state.evaluated_score());
                 }
                 next_beam.push(next_state);
             }
+            expanded_states.push(now_state);
         }
 
-        now_beam = next_beam;
-        best_state = now_beam.peek().unwrap().clone();
-        best_state.debug();
+        // warm_start の計画をたどった状態はビーム幅に関係なく次の層に残す
+        seed_state = match (seed_state, warm_start.get(t)) {
+            (Some(mut seed), Some(&action)) if seed.legal_actions().contains(&action) => {
+                seed.advance(action);
+                seed.evaluate_score();
+                if t == 0 {
+                    seed.set_first_action(action);
+                }
+                next_beam.push(seed.clone());
+                Some(seed)
+            }
+            _ => None,
+        };
+
+        // 壁に囲まれるなどして1手も進めないので、この層を最後の層として返す
+        if next_beam.is_empty() {
+            dead_ends.extend(expanded_states);
+            dead_ends.extend(std::iter::from_fn(|| now_beam.pop()));
+            return Some(best_first(dead_ends));
+        }
+        for state in expanded_states {
+            pool.recycle(state);
+        }
+        let generated = next_beam.len();
+        if dominance.enabled() {
+            let (kept, pruned) = prune_dominated(std::iter::from_fn(|| next_beam.pop()), dominance);
+            for state in pruned {
+                pool.recycle(state);
+            }
+            next_beam = BinaryHeap::from(kept);
+        }
+        for state in std::mem::replace(&mut now_beam, next_beam) {
+            pool.recycle(state);
+        }
+        let best_state = now_beam.peek().unwrap();
+        observer.on_layer(&LayerInfo {
+            pass: 0,
+            depth: t + 1,
+            best: best_state,
+            beam_size: now_beam.len(),
+            expanded,
+            generated,
+            elapsed: start.elapsed(),
+            duplicate_children,
+            scores: &scores,
+        });
         if best_state.is_done() {
             break;
         }
     }
-    best_state.first_action
+    // 最後の層を良い順に
+    dead_ends.extend(std::iter::from_fn(|| now_beam.pop()));
+    Some(best_first(dead_ends))
+}
+
+// 同点なら先に見つけた状態を前にする
+fn best_first<S: Ord>(mut states: Vec<S>) -> Vec<S> {
+    states.sort_by(|a, b| b.cmp(a));
+    states
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::exhaustive::*;
+    use crate::generator::strategy::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_beam_search_w2_d4() {
@@ -51,10 +344,10 @@ mod tests {
         let beam_width = 2;
         let beam_depth = 4;
         let action = beam_search(&state, beam_width, beam_depth);
-        assert_eq!(action, Some(1));
+        assert_eq!(action, Some(Action::Down));
     }
 
-        #[test]
+    #[test]
     fn test_beam_search_w4_d4() {
         let character = Pos::new(1, 1);
         let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
@@ -62,7 +355,7 @@ mod tests {
         let beam_width = 4;
         let beam_depth = 4;
         let action = beam_search(&state, beam_width, beam_depth);
-        assert_eq!(action, Some(0));
+        assert_eq!(action, Some(Action::Right));
     }
 
     #[test]
@@ -73,6 +366,114 @@ mod tests {
         let beam_width = 4;
         let beam_depth = 1;
         let action = beam_search(&state, beam_width, beam_depth);
-        assert_eq!(action, Some(3));
+        assert_eq!(action, Some(Action::Up));
+    }
+
+    #[test]
+    fn test_beam_search_plan_with_warm_start() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+
+        // 幅1だと貪欲に上(6点)へ行ってしまい最適解(20点)を逃す
+        let plan = beam_search_plan(&state, 1, 4, &[]);
+        assert_eq!(plan.first(), Some(&Action::Up));
+
+        let plan = beam_search_plan(
+            &state,
+            1,
+            4,
+            &[Action::Right, Action::Down, Action::Left, Action::Left],
+        );
+        assert_eq!(
+            plan,
+            vec![Action::Right, Action::Down, Action::Left, Action::Left]
+        );
+
+        let best = beam_search_until_with_warm_start(&state, 1, 4, None, &plan, &mut NoopObserver)
+            .unwrap();
+        assert_eq!(best.evaluated_score, 20);
+    }
+
+    #[test]
+    fn test_dead_end_is_a_candidate() {
+        // 右の9を取ると行き止まり。下に進むと続くが点は低い
+        let state = State::parse(
+            "
+            @> 9 #
+            1 # #
+            1 1 1
+            ",
+        )
+        .unwrap();
+        let optimum = exhaustive_search(&state, 4).unwrap();
+        assert_eq!(optimum.evaluated_score, 9);
+        for beam_width in 1..=4 {
+            let best = beam_search_until(&state, beam_width, 4, None, &mut NoopObserver).unwrap();
+            assert_eq!(best.evaluated_score, 9);
+            assert_eq!(best.first_action, Some(Action::Right));
+        }
+    }
+
+    #[test]
+    fn test_beam_search_without_legal_actions() {
+        let state = State::parse(
+            "
+            # 9 9
+            @ # 9
+            ",
+        )
+        .unwrap();
+        assert_eq!(beam_search(&state, 2, 4), None);
+    }
+
+    #[test]
+    fn test_beam_search_with_observer() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let mut layers = vec![];
+        let action = beam_search_with_observer(&state, 2, 4, &mut |info: &LayerInfo| {
+            layers.push((info.depth, info.best_score(), info.beam_size, info.expanded));
+        });
+        assert_eq!(action, Some(Action::Down));
+        assert_eq!(
+            layers,
+            vec![(1, 6, 4, 1), (2, 12, 6, 2), (3, 17, 5, 2), (4, 17, 6, 2)]
+        );
+    }
+
+    #[test]
+    fn test_beam_search_top_k() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let plans = beam_search_top_k(&state, 4, 4, 3);
+
+        assert_eq!(plans.len(), 3);
+        assert_eq!(plans[0].actions, beam_search_plan(&state, 4, 4, &[]));
+        assert_eq!(plans[0].score(), 20);
+        let mut keys = HashSet::new();
+        for plan in plans.iter() {
+            assert!(keys.insert(plan.state.hash_key()));
+            let frames = crate::replay::replay(&state, &plan.actions);
+            assert_eq!(frames.last().unwrap().game_score, plan.score());
+        }
+        assert!(plans.windows(2).all(|w| w[0].score() >= w[1].score()));
+
+        // 1手で行ける盤面は4通りしかない
+        assert_eq!(beam_search_top_k(&state, 4, 1, 10).len(), 4);
+    }
+
+    proptest! {
+        // 行動は高々4通りなので、幅 4^depth なら1つも枝刈りしない
+        #[test]
+        fn prop_wide_beam_is_exhaustive(state in arb_state(false), beam_depth in 1..=4usize) {
+            let beam_width = 4usize.pow(beam_depth as u32);
+            let optimum = exhaustive_search(&state, beam_depth).unwrap();
+            let best =
+                beam_search_until(&state, beam_width, beam_depth, None, &mut NoopObserver).unwrap();
+            prop_assert_eq!(best.evaluated_score, optimum.evaluated_score);
+        }
     }
 }
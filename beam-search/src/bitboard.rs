@@ -0,0 +1,228 @@
+use crate::action::*;
+use crate::search_state::*;
+use crate::state::*;
+use order_by::OrderBy;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+// 盤面の大きさと各マスの点。全ての状態で共有する
+#[derive(Debug)]
+struct Board {
+    height: usize,
+    width: usize,
+    points: Vec<usize>,
+    end_turn: usize,
+}
+
+// 128マス以下の単純な迷路 (壁や再生などのルールなし) 専用の状態
+// どのマスの点が残っているかを u128 のビットで持ち、位置はマス番号 y * width + x に詰める
+// 複製しても確保が起きないので State より速い
+#[derive(Clone, Debug, OrderBy)]
+#[order_by(evaluated_score, desc)]
+pub struct BitState {
+    pub first_action: Option<Action>,
+    character: u8,
+    // 点が残っているマスのビットが立つ
+    remaining: u128,
+    pub turn: usize,
+    pub game_score: usize,
+    pub evaluated_score: usize,
+    board: Arc<Board>,
+}
+
+impl BitState {
+    pub const MAX_CELLS: usize = u128::BITS as usize;
+
+    // 追加のルールがない State だけ変換できる
+    pub fn from_state(state: &State) -> Result<BitState, String> {
+        let (height, width) = (state.height(), state.width());
+        if height * width > Self::MAX_CELLS {
+            return Err(format!(
+                "{}x{} board has more than {} cells",
+                height,
+                width,
+                Self::MAX_CELLS
+            ));
+        }
+        let rules = &state.rules;
+        if rules
+            .tiles
+            .iter()
+            .flatten()
+            .any(|&tile| tile != Tile::Floor)
+            || rules.actions != Action::MOVES
+            || rules.regrowth.is_some()
+            || rules.reward != RewardSchedule::Constant
+            || rules.spawn.is_some()
+        {
+            return Err("only plain mazes can be converted".to_string());
+        }
+
+        let points = state.points.iter().collect::<Vec<usize>>();
+        let remaining = points
+            .iter()
+            .enumerate()
+            .filter(|&(_, &point)| point > 0)
+            .fold(0, |mask, (i, _)| mask | 1 << i);
+        Ok(BitState {
+            first_action: state.first_action,
+            character: (state.character.y * width + state.character.x) as u8,
+            remaining,
+            turn: state.turn,
+            game_score: state.game_score,
+            evaluated_score: state.evaluated_score,
+            board: Arc::new(Board {
+                height,
+                width,
+                points,
+                end_turn: state.end_turn,
+            }),
+        })
+    }
+
+    pub fn character(&self) -> Pos {
+        let i = self.character as usize;
+        Pos::new(i / self.board.width, i % self.board.width)
+    }
+
+    pub fn point(&self, pos: Pos) -> usize {
+        let i = pos.y * self.board.width + pos.x;
+        if self.remaining >> i & 1 == 1 {
+            self.board.points[i]
+        } else {
+            0
+        }
+    }
+
+    // 行動後のマス番号。盤面の外なら None
+    fn destination(&self, action: Action) -> Option<u8> {
+        let (dy, dx) = action.delta()?;
+        let character = self.character();
+        let ty = character.y.checked_add_signed(dy)?;
+        let tx = character.x.checked_add_signed(dx)?;
+        if self.board.height <= ty || self.board.width <= tx {
+            return None;
+        }
+        Some((ty * self.board.width + tx) as u8)
+    }
+}
+
+impl SearchState for BitState {
+    fn legal_actions(&self) -> Vec<Action> {
+        Action::MOVES
+            .into_iter()
+            .filter(|&action| self.destination(action).is_some())
+            .collect()
+    }
+
+    fn advance(&mut self, action: Action) {
+        self.character = self.destination(action).unwrap();
+        let bit = 1 << self.character;
+        if self.remaining & bit != 0 {
+            self.game_score += self.board.points[self.character as usize];
+            self.remaining &= !bit;
+        }
+        self.turn += 1;
+    }
+
+    fn evaluate_score(&mut self) {
+        self.evaluated_score = self.game_score;
+    }
+
+    fn evaluated_score(&self) -> usize {
+        self.evaluated_score
+    }
+
+    fn first_action(&self) -> Option<Action> {
+        self.first_action
+    }
+
+    fn set_first_action(&mut self, action: Action) {
+        self.first_action = Some(action);
+    }
+
+    fn hash_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.character.hash(&mut hasher);
+        self.remaining.hash(&mut hasher);
+        self.turn.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn is_done(&self) -> bool {
+        self.turn >= self.board.end_turn
+    }
+}
+
+// State と同じ形式で出力する
+impl std::fmt::Display for BitState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# turn {}", self.turn)?;
+        writeln!(f, "score {}", self.evaluated_score)?;
+        let character = self.character();
+        for y in 0..self.board.height {
+            for x in 0..self.board.width {
+                let pos = Pos::new(y, x);
+                if pos == character {
+                    write!(f, "@ ")?;
+                } else {
+                    write!(f, "{} ", self.point(pos))?;
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beam_search::*;
+    use crate::generator::*;
+
+    #[test]
+    fn test_from_state() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let bit_state = BitState::from_state(&state).unwrap();
+
+        assert_eq!(bit_state.character(), character);
+        assert_eq!(bit_state.to_string(), state.to_string());
+        assert_eq!(
+            SearchState::legal_actions(&bit_state),
+            state.legal_actions()
+        );
+
+        assert!(BitState::from_state(&generate(12, 11, 4, 0)).is_err());
+        assert!(BitState::from_state(&state.clone().with_regrowth(1)).is_err());
+        assert!(BitState::from_state(&State::parse("@ # 1").unwrap()).is_err());
+    }
+
+    #[test]
+    fn test_advance_matches_state() {
+        let mut state = generate(5, 5, 20, 0);
+        let mut bit_state = BitState::from_state(&state).unwrap();
+        for t in 0..20 {
+            let legal_actions = state.legal_actions();
+            let action = legal_actions[t % legal_actions.len()];
+            state.advance(action);
+            state.evaluate_score();
+            bit_state.advance(action);
+            bit_state.evaluate_score();
+            assert_eq!(bit_state.to_string(), state.to_string());
+            assert_eq!(bit_state.is_done(), state.is_done());
+        }
+    }
+
+    #[test]
+    fn test_beam_search_matches_state() {
+        for seed in 0..10 {
+            let state = generate(6, 6, 12, seed);
+            let bit_state = BitState::from_state(&state).unwrap();
+            assert_eq!(beam_search(&bit_state, 8, 12), beam_search(&state, 8, 12));
+        }
+    }
+}
@@ -1,66 +1,353 @@
 #![allow(unused)]
+use crate::action::*;
+use crate::beam_search::*;
+use crate::dominance::*;
+use crate::observer::*;
+use crate::pool::*;
 use crate::state::*;
 use std::cell::RefCell;
 use std::rc::Rc;
 // Ref. ゲームで学ぶ探索アルゴリズム実践入門
 
 use std::collections::BinaryHeap;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::time::Instant;
 
-fn chokudai_search(
+pub fn chokudai_search(
     state: &State,
     beam_width: usize,
     beam_depth: usize,
     beam_number: usize,
-) -> Option<usize> {
-    let mut beam = vec![];
-    for t in 0..beam_depth + 1 {
-        beam.push(BinaryHeap::new());
+) -> Option<Action> {
+    chokudai_search_with_observer(
+        state,
+        beam_width,
+        beam_depth,
+        beam_number,
+        &mut NoopObserver,
+    )
+}
+
+pub fn chokudai_search_with_observer<O: SearchObserver>(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+    observer: &mut O,
+) -> Option<Action> {
+    ChokudaiSearch::new(state, beam_width, beam_depth).run_with_observer(beam_number, observer)
+}
+
+// 各深さのビームを呼び出しをまたいで持ち続ける chokudaiサーチ
+// 他の処理の合間やターンをまたいで少しずつ周回を重ねられる
+pub struct ChokudaiSearch {
+    root: State,
+    beams: ChokudaiBeams,
+    beam_width: usize,
+    passes: usize,
+    pool: StatePool,
+}
+
+impl ChokudaiSearch {
+    pub fn new(state: &State, beam_width: usize, beam_depth: usize) -> Self {
+        ChokudaiSearch {
+            root: state.clone(),
+            beams: ChokudaiBeams::new(state, beam_depth),
+            beam_width,
+            passes: 0,
+            pool: StatePool::new(beam_capacity(state, beam_width)),
+        }
     }
-    beam[0].push(state.clone());
-    for cnt in 0..beam_number {
+
+    // 保存しておいたビームから続きを回すセッションを作る。root はビームを作ったときの初期状態
+    // ビームは深さ0の分を含めて1本以上必要
+    pub fn from_beams(
+        root: &State,
+        beams: ChokudaiBeams,
+        beam_width: usize,
+    ) -> Result<Self, String> {
+        if beams.beams.is_empty() {
+            return Err("chokudai beams must contain at least the depth-0 beam".to_string());
+        }
+        Ok(ChokudaiSearch {
+            root: root.clone(),
+            beams,
+            beam_width,
+            passes: 0,
+            pool: StatePool::new(beam_capacity(root, beam_width)),
+        })
+    }
+
+    // pool を StatePool::new(capacity) に差し替える
+    pub fn with_pool_capacity(mut self, capacity: usize) -> Self {
+        self.pool = StatePool::new(capacity);
+        self
+    }
+
+    // さらに beam_number 周して、その時点の最良の1手目を返す
+    pub fn run(&mut self, beam_number: usize) -> Option<Action> {
+        self.run_with_observer(beam_number, &mut NoopObserver)
+    }
+
+    // observer に渡す pass はこのセッションで通算何周目か
+    pub fn run_with_observer<O: SearchObserver>(
+        &mut self,
+        beam_number: usize,
+        observer: &mut O,
+    ) -> Option<Action> {
+        self.run_passes(beam_number, &NoDominance, observer)
+    }
+
+    // 各層を展開するたびに、その次の層から他の状態に支配される状態を捨てる
+    pub fn run_with_dominance<D: Dominance<State>>(
+        &mut self,
+        beam_number: usize,
+        dominance: &D,
+    ) -> Option<Action> {
+        self.run_passes(beam_number, dominance, &mut NoopObserver)
+    }
+
+    fn run_passes<D: Dominance<State>, O: SearchObserver>(
+        &mut self,
+        beam_number: usize,
+        dominance: &D,
+        observer: &mut O,
+    ) -> Option<Action> {
+        run_passes(
+            &mut self.beams.beams,
+            &mut self.pool,
+            self.beam_width,
+            self.passes..self.passes + beam_number,
+            dominance,
+            observer,
+        );
+        self.passes += beam_number;
+        self.best_action()
+    }
+
+    pub fn best(&self) -> Option<&State> {
+        self.beams.best()
+    }
+
+    pub fn best_action(&self) -> Option<Action> {
+        self.beams.best_action()
+    }
+
+    // 一番深いところまで進んだ状態から、盤面が重複しないように良い順に k 個の計画を返す
+    pub fn top_k(&self, k: usize) -> Vec<Plan> {
+        let Some(beam) = self.beams.beams.iter().rev().find(|beam| !beam.is_empty()) else {
+            return vec![];
+        };
+        let mut beam = beam.clone();
+        top_k_plans(&self.root, std::iter::from_fn(|| beam.pop()), k)
+    }
+
+    pub fn root(&self) -> &State {
+        &self.root
+    }
+
+    pub fn beams(&self) -> &ChokudaiBeams {
+        &self.beams
+    }
+
+    pub fn into_beams(self) -> ChokudaiBeams {
+        self.beams
+    }
+
+    // これまでに回した周回数
+    pub fn passes(&self) -> usize {
+        self.passes
+    }
+
+    // 探索した結果を捨てて root から探索し直す
+    pub fn reset(&mut self) {
+        let beam_depth = self.beams.beams.len() - 1;
+        for beam in self.beams.beams.iter_mut() {
+            for state in beam.drain() {
+                self.pool.recycle(state);
+            }
+        }
+        self.beams = ChokudaiBeams::new(&self.root, beam_depth);
+        self.passes = 0;
+    }
+
+    // 実際に action を選んでターンを進める
+    // action から始まる状態だけを1層ずつ浅くして残すので、次のターンはその続きから探索できる
+    pub fn advance(&mut self, action: Action) {
+        let root_len = self.root.actions.len();
+        self.root.advance(action);
+        self.root.evaluate_score();
+        self.root.first_action = None;
+
+        let beam_depth = self.beams.beams.len() - 1;
+        let mut beams = vec![BinaryHeap::new(); beam_depth + 1];
+        for (t, beam) in self.beams.beams.iter_mut().enumerate() {
+            for mut state in beam.drain() {
+                if t == 0 || state.actions[root_len] != action {
+                    self.pool.recycle(state);
+                    continue;
+                }
+                state.first_action = state.actions.get(root_len + 1).copied();
+                beams[t - 1].push(state);
+            }
+        }
+        // まだ action の先を1つも生成していなければ新しい root から始める
+        if beams.iter().all(|beam| beam.is_empty()) {
+            beams[0].push(self.root.clone());
+        }
+        self.beams = ChokudaiBeams { beams };
+    }
+}
+
+// 各深さのビーム。保存しておけば ChokudaiSearch::from_beams (chokudai_search_resume) で続きの周回を回せる
+// BinaryHeap は中の並び順のまま保存・復元されるので、続きから回しても通しで回したのと同じ結果になる
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChokudaiBeams {
+    pub beams: Vec<BinaryHeap<State>>,
+}
+
+impl ChokudaiBeams {
+    pub fn new(state: &State, beam_depth: usize) -> Self {
+        let mut beams = vec![];
+        for t in 0..beam_depth + 1 {
+            beams.push(BinaryHeap::new());
+        }
+        beams[0].push(state.clone());
+        ChokudaiBeams { beams }
+    }
+
+    // 一番深いところまで進んだ状態のうち最良のもの
+    pub fn best(&self) -> Option<&State> {
+        self.beams.iter().rev().find_map(|now_beam| now_beam.peek())
+    }
+
+    pub fn best_action(&self) -> Option<Action> {
+        self.best().and_then(|state| state.first_action)
+    }
+}
+
+pub fn chokudai_search_top_k(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+    k: usize,
+) -> Vec<Plan> {
+    let mut search = ChokudaiSearch::new(state, beam_width, beam_depth);
+    search.run(beam_number);
+    search.top_k(k)
+}
+
+// beam_number 周した後のビームを返す
+pub fn chokudai_search_beams(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    beam_number: usize,
+) -> ChokudaiBeams {
+    let mut search = ChokudaiSearch::new(state, beam_width, beam_depth);
+    search.run(beam_number);
+    search.into_beams()
+}
+
+// 保存しておいたビームからさらに beam_number 周する。state はビームを作ったときの初期状態
+pub fn chokudai_search_resume(
+    state: &State,
+    beams: &mut ChokudaiBeams,
+    beam_width: usize,
+    beam_number: usize,
+) -> Result<Option<Action>, String> {
+    let saved = std::mem::replace(beams, ChokudaiBeams { beams: vec![] });
+    let mut search = ChokudaiSearch::from_beams(state, saved, beam_width)?;
+    let action = search.run(beam_number);
+    *beams = search.into_beams();
+    Ok(action)
+}
+
+fn run_passes<D: Dominance<State>, O: SearchObserver>(
+    beam: &mut [BinaryHeap<State>],
+    pool: &mut StatePool,
+    beam_width: usize,
+    passes: Range<usize>,
+    dominance: &D,
+    observer: &mut O,
+) {
+    let beam_depth = beam.len() - 1;
+    for cnt in passes {
         for t in 0..beam_depth {
+            let start = Instant::now();
+            let mut expanded = 0;
+            let mut generated = 0;
+            let mut seen = HashSet::new();
+            let mut duplicate_children = 0;
+            let mut scores = vec![];
             for i in 0..beam_width {
                 if beam[t].is_empty() {
                     break;
                 }
-                let now_state = beam[t].pop().unwrap();
-                if now_state.is_done() {
+                // 終了した状態は取り出さずに残しておく。最後に最良の状態として選べるように
+                if beam[t].peek().unwrap().is_done() {
                     break;
                 }
+                let now_state = beam[t].pop().unwrap();
+                expanded += 1;
 
                 let legal_actions = now_state.legal_actions();
                 for action in legal_actions {
-                    let mut next_state = now_state.clone();
+                    let mut next_state = pool.clone_state(&now_state);
                     next_state.advance(action);
                     next_state.evaluate_score();
                     if t == 0 {
                         next_state.first_action = Some(action);
                     }
+                    if observer.detailed() {
+                        if !seen.insert(next_state.hash_key()) {
+                            duplicate_children += 1;
+                        }
+                        scores.push(next_state.evaluated_score);
+                    }
+                    generated += 1;
                     beam[t + 1].push(next_state);
                 }
+                pool.recycle(now_state);
             }
-        }
-    }
 
-    // for state in beam[beam_depth].iter() {
-    //     state.debug();
-    // }
+            if generated > 0 && dominance.enabled() {
+                let (kept, pruned) =
+                    prune_dominated(std::iter::from_fn(|| beam[t + 1].pop()), dominance);
+                for state in pruned {
+                    pool.recycle(state);
+                }
+                beam[t + 1] = BinaryHeap::from(kept);
+            }
 
-    for now_beam in beam.iter().rev() {
-        if !now_beam.is_empty() {
-            return now_beam.peek().unwrap().first_action;
+            if generated > 0 {
+                observer.on_layer(&LayerInfo {
+                    pass: cnt,
+                    depth: t + 1,
+                    best: beam[t + 1].peek().unwrap(),
+                    beam_size: beam[t + 1].len(),
+                    expanded,
+                    generated,
+                    elapsed: start.elapsed(),
+                    duplicate_children,
+                    scores: &scores,
+                });
+            }
         }
     }
-    None
 }
 
 // RefCell遅いので、参考までに
-fn chokudai_search_with_refcell(
+// どれくらい遅いかは cargo bench --bench search で chokudai_search と比べられる
+pub fn chokudai_search_with_refcell(
     state: &State,
     beam_width: usize,
     beam_depth: usize,
     beam_number: usize,
-) -> Option<usize> {
+) -> Option<Action> {
     let mut beam = vec![];
     for t in 0..beam_depth + 1 {
         beam.push(Rc::new(RefCell::new(BinaryHeap::new())));
@@ -74,10 +361,10 @@ fn chokudai_search_with_refcell(
                 if now_beam.borrow().is_empty() {
                     break;
                 }
-                let now_state = now_beam.borrow_mut().pop().unwrap();
-                if now_state.is_done() {
+                if now_beam.borrow().peek().unwrap().is_done() {
                     break;
                 }
+                let now_state = now_beam.borrow_mut().pop().unwrap();
 
                 let legal_actions = now_state.legal_actions();
                 for action in legal_actions {
@@ -108,6 +395,9 @@ fn chokudai_search_with_refcell(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::exhaustive::*;
+    use crate::generator::strategy::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_beam_search_w1_d1_n4() {
@@ -118,7 +408,7 @@ mod tests {
         let beam_depth = 1;
         let beam_number = 4;
         let action = chokudai_search(&state, beam_width, beam_depth, beam_number);
-        assert_eq!(action, Some(3));
+        assert_eq!(action, Some(Action::Up));
     }
 
     #[test]
@@ -130,7 +420,7 @@ mod tests {
         let beam_depth = 2;
         let beam_number = 2;
         let action = chokudai_search(&state, beam_width, beam_depth, beam_number);
-        assert_eq!(action, Some(1));
+        assert_eq!(action, Some(Action::Down));
     }
 
     #[test]
@@ -142,7 +432,7 @@ mod tests {
         let beam_depth = 3;
         let beam_number = 2;
         let action = chokudai_search(&state, beam_width, beam_depth, beam_number);
-        assert_eq!(action, Some(1));
+        assert_eq!(action, Some(Action::Down));
     }
 
     #[test]
@@ -154,6 +444,121 @@ mod tests {
         let beam_depth = 4;
         let beam_number = 2;
         let action = chokudai_search(&state, beam_width, beam_depth, beam_number);
-        assert_eq!(action, Some(1));
+        assert_eq!(action, Some(Action::Down));
+    }
+
+    #[test]
+    fn test_refcell_matches() {
+        for seed in 0..5 {
+            let state = crate::generator::generate(5, 5, 10, seed);
+            assert_eq!(
+                chokudai_search_with_refcell(&state, 2, 10, 3),
+                chokudai_search(&state, 2, 10, 3)
+            );
+        }
+    }
+
+    #[test]
+    fn test_top_k() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let plans = chokudai_search_top_k(&state, 2, 4, 4, 5);
+
+        assert_eq!(plans.len(), 5);
+        assert_eq!(
+            plans[0].actions[0],
+            chokudai_search(&state, 2, 4, 4).unwrap()
+        );
+        let mut keys = HashSet::new();
+        for plan in plans.iter() {
+            assert_eq!(plan.actions.len(), 4);
+            assert!(keys.insert(plan.state.hash_key()));
+        }
+        assert!(plans.windows(2).all(|w| w[0].score() >= w[1].score()));
+    }
+
+    #[test]
+    fn test_session() {
+        let state = crate::generator::generate(5, 5, 10, 0);
+        let expect = chokudai_search(&state, 2, 10, 3);
+
+        let mut search = ChokudaiSearch::new(&state, 2, 10);
+        assert_eq!(search.best_action(), None);
+        search.run(1);
+        assert_eq!(search.run(2), expect);
+        assert_eq!(search.passes(), 3);
+        assert_eq!(search.best().unwrap().first_action, expect);
+
+        search.reset();
+        assert_eq!(search.passes(), 0);
+        assert_eq!(search.best_action(), None);
+        assert_eq!(search.run(3), expect);
+    }
+
+    #[test]
+    fn test_session_over_turns() {
+        let state = crate::generator::generate(5, 5, 10, 0);
+        let mut search = ChokudaiSearch::new(&state, 2, 4);
+        let mut now_state = state.clone();
+        while !now_state.is_done() {
+            let action = search.run(2).unwrap();
+            assert!(now_state.legal_actions().contains(&action));
+            now_state.advance(action);
+            now_state.evaluate_score();
+            search.advance(action);
+            assert_eq!(search.root().to_string(), now_state.to_string());
+            // 前のターンに探索した続きが残っている
+            if !now_state.is_done() {
+                assert!(search.best().is_some());
+            }
+        }
+    }
+
+    #[test]
+    fn test_resume() {
+        for seed in 0..5 {
+            let state = crate::generator::generate(5, 5, 10, seed);
+            let mut beams = chokudai_search_beams(&state, 2, 10, 1);
+            assert_eq!(
+                chokudai_search_resume(&state, &mut beams, 2, 2).unwrap(),
+                chokudai_search(&state, 2, 10, 3)
+            );
+
+            // セッションに戻しても続きから回せる
+            let beams = chokudai_search_beams(&state, 2, 10, 1);
+            let mut search = ChokudaiSearch::from_beams(&state, beams, 2).unwrap();
+            assert_eq!(search.run(2), chokudai_search(&state, 2, 10, 3));
+            assert_eq!(search.top_k(1)[0].actions, search.best().unwrap().actions);
+
+            // 読み込んだビームが空なら続きを回さずにエラーにする
+            let empty = ChokudaiBeams { beams: vec![] };
+            assert!(ChokudaiSearch::from_beams(&state, empty.clone(), 2).is_err());
+            let mut empty = empty;
+            assert!(chokudai_search_resume(&state, &mut empty, 2, 1).is_err());
+        }
+    }
+
+    proptest! {
+        // 幅 4^depth なら1周で全ての行動列を調べるので、選んだ行動の先で最適値に届く
+        #[test]
+        fn prop_wide_chokudai_is_exhaustive(state in arb_state(false), beam_depth in 1..=4usize) {
+            let beam_width = 4usize.pow(beam_depth as u32);
+            let optimum = exhaustive_search(&state, beam_depth).unwrap();
+            let action = chokudai_search(&state, beam_width, beam_depth, 1).unwrap();
+            prop_assert!(state.legal_actions().contains(&action));
+
+            let mut next_state = state.clone();
+            next_state.advance(action);
+            next_state.evaluate_score();
+            let score = if beam_depth == 1 || next_state.is_done() {
+                next_state.evaluated_score
+            } else {
+                exhaustive_search(&next_state, beam_depth - 1)
+                    .unwrap()
+                    .evaluated_score
+            };
+            prop_assert_eq!(score, optimum.evaluated_score);
+        }
     }
 }
@@ -0,0 +1,261 @@
+use crate::search_state::*;
+use crate::state::*;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+// 状態の間の支配関係。a が b を支配するなら、b から先どう進めても a から同じ以上のスコアが取れるので b を捨ててよい
+pub trait Dominance<S> {
+    // 支配関係を調べるのは同じキーの状態同士だけ
+    fn key(&self, state: &S) -> u64;
+    fn dominates(&self, a: &S, b: &S) -> bool;
+
+    // false なら枝刈りそのものを飛ばす
+    fn enabled(&self) -> bool {
+        true
+    }
+}
+
+// 枝刈りしないデフォルト
+pub struct NoDominance;
+
+impl<S> Dominance<S> for NoDominance {
+    fn key(&self, _state: &S) -> u64 {
+        0
+    }
+
+    fn dominates(&self, _a: &S, _b: &S) -> bool {
+        false
+    }
+
+    fn enabled(&self) -> bool {
+        false
+    }
+}
+
+// 迷路の State 用。位置・ターン・再生待ちが同じ状態同士を比べる
+// 得点が一定 (RewardSchedule::Constant) で再生も湧きもないとき:
+//   a が取ったマスが b の取ったマスを全て含み、a のスコアが b のスコア + (b だけに残っている点) 以上なら a が b を支配する
+//   b がこの先取れる点のうち a に取れないのは b だけに残っている点なので、a は b 以上のスコアになる
+// それ以外 (減衰・倍率表・再生・湧き) では取る順番やターンで点が変わるので、
+//   a のスコアが b 以上、かつ b に残っている点が a にも全て残っているときだけ支配する
+pub struct MazeDominance;
+
+impl Dominance<State> for MazeDominance {
+    fn key(&self, state: &State) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        state.character.hash(&mut hasher);
+        state.turn.hash(&mut hasher);
+        state.regrowing.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn dominates(&self, a: &State, b: &State) -> bool {
+        if a.character != b.character
+            || a.turn != b.turn
+            || a.regrowing != b.regrowing
+            || a.game_score < b.game_score
+        {
+            return false;
+        }
+        let rules = &a.rules;
+        let collected_superset = rules.reward == RewardSchedule::Constant
+            && rules.regrowth.is_none()
+            && rules.spawn.is_none();
+        if collected_superset {
+            let mut only_b = 0;
+            for (pa, pb) in a.points.iter().zip(b.points.iter()) {
+                // a に残っている点は b にも同じだけ残っていなければならない
+                if pa > 0 && pa != pb {
+                    return false;
+                }
+                only_b += pb - pa;
+            }
+            a.game_score >= b.game_score + only_b
+        } else {
+            a.points
+                .iter()
+                .zip(b.points.iter())
+                .all(|(pa, pb)| pa >= pb)
+        }
+    }
+}
+
+// states を良い順に渡すと、先に残した状態に支配されるものを取り除く
+// 返り値は (残した状態, 取り除いた状態)。どちらも渡した順のまま
+pub fn prune_dominated<S: SearchState, D: Dominance<S>>(
+    states: impl IntoIterator<Item = S>,
+    dominance: &D,
+) -> (Vec<S>, Vec<S>) {
+    let mut kept: Vec<S> = vec![];
+    let mut pruned = vec![];
+    let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+    for state in states {
+        let bucket = buckets.entry(dominance.key(&state)).or_default();
+        if bucket
+            .iter()
+            .any(|&i| dominance.dominates(&kept[i], &state))
+        {
+            pruned.push(state);
+            continue;
+        }
+        bucket.push(kept.len());
+        kept.push(state);
+    }
+    (kept, pruned)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::*;
+    use crate::beam_search::*;
+    use crate::chokudai_search::*;
+    use crate::exhaustive::*;
+    use crate::generator::strategy::*;
+    use crate::generator::*;
+    use crate::observer::*;
+    use proptest::prelude::*;
+
+    fn play(state: &State, actions: &[Action]) -> State {
+        let mut state = state.clone();
+        for &action in actions {
+            state.advance(action);
+            state.evaluate_score();
+        }
+        state
+    }
+
+    #[test]
+    fn test_maze_dominance() {
+        // 同じマスを違う順に取って (0, 0) に戻る。減衰があるので大きい点を先に取った方が良い
+        let state = State::parse(
+            "
+            @ 2
+            3 0
+            ",
+        )
+        .unwrap()
+        .with_reward(RewardSchedule::Decay(0.5));
+        let a = play(
+            &state,
+            &[Action::Right, Action::Down, Action::Left, Action::Up],
+        );
+        let b = play(
+            &state,
+            &[Action::Down, Action::Right, Action::Up, Action::Left],
+        );
+        assert_eq!((a.game_score, b.game_score), (3, 4));
+        assert_eq!(MazeDominance.key(&a), MazeDominance.key(&b));
+        assert!(MazeDominance.dominates(&b, &a));
+        assert!(!MazeDominance.dominates(&a, &b));
+
+        // 取ったマスが違えばスコアが高くても支配しない
+        let c = play(&state, &[Action::Right, Action::Left]);
+        let d = play(&state, &[Action::Down, Action::Up]);
+        assert!(!MazeDominance.dominates(&d, &c));
+
+        let (kept, pruned) = prune_dominated([b, a, c, d], &MazeDominance);
+        assert_eq!(kept.len(), 3);
+        assert_eq!(pruned[0].game_score, 3);
+    }
+
+    #[test]
+    fn test_collected_superset_dominates() {
+        // (0, 0) に戻ってくる2手。a は 5 を取っていて、b は何も取っていない
+        let state = State::parse(
+            "
+            @ 5 0
+            0 0 1
+            ",
+        )
+        .unwrap();
+        let a = play(&state, &[Action::Right, Action::Left]);
+        let b = play(&state, &[Action::Down, Action::Up]);
+        assert_eq!((a.game_score, b.game_score), (5, 0));
+        assert!(MazeDominance.dominates(&a, &b));
+        assert!(!MazeDominance.dominates(&b, &a));
+
+        let (kept, pruned) = prune_dominated([a, b], &MazeDominance);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(pruned[0].game_score, 0);
+
+        // 減衰があると 5 を後で取る方が悪いとは限らないので支配しない
+        let state = state.with_reward(RewardSchedule::Decay(0.5));
+        let a = play(&state, &[Action::Right, Action::Left]);
+        let b = play(&state, &[Action::Down, Action::Up]);
+        assert!(!MazeDominance.dominates(&a, &b));
+    }
+
+    #[test]
+    fn test_pruning_shrinks_beam() {
+        let state = State::parse(
+            "
+            0 5 0
+            @ 0 3
+            0 2 0
+            ",
+        )
+        .unwrap();
+        let mut sizes = vec![];
+        let best =
+            beam_search_with_dominance(&state, 256, 4, &MazeDominance, &mut |info: &LayerInfo| {
+                sizes.push(info.beam_size)
+            })
+            .unwrap();
+        let mut full_sizes = vec![];
+        beam_search_with_observer(&state, 256, 4, &mut |info: &LayerInfo| {
+            full_sizes.push(info.beam_size)
+        });
+        assert!(sizes.iter().zip(&full_sizes).all(|(a, b)| a <= b));
+        assert!(sizes.last() < full_sizes.last());
+        assert_eq!(
+            best.evaluated_score,
+            exhaustive_search(&state, 4).unwrap().evaluated_score
+        );
+    }
+
+    #[test]
+    fn test_chokudai_search_with_dominance() {
+        for seed in 0..5 {
+            let state = generate(4, 4, 4, seed).with_reward(RewardSchedule::Decay(0.9));
+            let optimum = exhaustive_search(&state, 4).unwrap();
+            let mut search = ChokudaiSearch::new(&state, 256, 4);
+            search.run_with_dominance(1, &MazeDominance);
+            assert_eq!(
+                search.best().unwrap().evaluated_score,
+                optimum.evaluated_score
+            );
+        }
+    }
+
+    proptest! {
+        // 支配される状態しか捨てないので、幅 4^depth なら全探索と同じ値になる
+        #[test]
+        fn prop_pruned_wide_beam_is_exhaustive(
+            state in arb_state(true),
+            beam_depth in 1..=4usize,
+            decay in prop::bool::ANY,
+        ) {
+            let state = if decay {
+                state.with_reward(RewardSchedule::Decay(0.8))
+            } else {
+                state
+            };
+            let beam_width = 4usize.pow(beam_depth as u32);
+            // 1手も進めない迷路は除く
+            let Some(optimum) = exhaustive_search(&state, beam_depth) else {
+                return Ok(());
+            };
+            let best = beam_search_with_dominance(
+                &state,
+                beam_width,
+                beam_depth,
+                &MazeDominance,
+                &mut NoopObserver,
+            )
+            .unwrap();
+            prop_assert_eq!(best.evaluated_score, optimum.evaluated_score);
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use crate::action::*;
+use crate::rng::*;
+use crate::state::*;
+
+// 毎ターン search で1手選んで進めるのを、ゲームが終わるまで繰り返す
+// search が None を返したらその時点で打ち切る
+pub fn play<F: FnMut(&State) -> Option<Action>>(state: &State, mut search: F) -> State {
+    let mut state = state.clone();
+    while !state.is_done() {
+        match search(&state) {
+            Some(action) => state.advance(action),
+            None => break,
+        }
+    }
+    state
+}
+
+// planner は現在の状態と前回の計画の残りを受け取り、行動列を返す
+// warm_start が false なら前回の計画の残りは渡さない (常に空)
+pub fn play_with_plan<F: FnMut(&State, &[Action]) -> Vec<Action>>(
+    state: &State,
+    warm_start: bool,
+    mut planner: F,
+) -> State {
+    let mut state = state.clone();
+    let mut tail = vec![];
+    while !state.is_done() {
+        let plan = planner(&state, &tail);
+        let Some(&action) = plan.first() else {
+            break;
+        };
+        state.advance(action);
+        if warm_start {
+            tail = plan[1..].to_vec();
+        }
+    }
+    state
+}
+
+// 湧きのある盤面用。毎ターン行動の後に seed から作った乱数で湧きを適用する
+pub fn play_stochastic<F: FnMut(&State) -> Option<Action>>(
+    state: &State,
+    seed: u64,
+    mut search: F,
+) -> State {
+    let mut rng = XorShift::new(seed);
+    let mut state = state.clone();
+    while !state.is_done() {
+        match search(&state) {
+            Some(action) => {
+                state.advance(action);
+                state.spawn_random(&mut rng);
+            }
+            None => break,
+        }
+    }
+    state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beam_search::*;
+    use crate::chokudai_search::*;
+    use crate::generator::*;
+    use crate::replay::*;
+
+    #[test]
+    fn test_play() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let final_state = play(&state, |state| beam_search(state, 4, 4));
+
+        assert!(final_state.is_done());
+        assert_eq!(final_state.actions.len(), 4);
+        assert_eq!(final_state.game_score, 20);
+
+        // 行動履歴をリプレイすると同じ結果になる
+        let frames = replay(&state, &final_state.actions);
+        assert_eq!(frames.last().unwrap().game_score, final_state.game_score);
+    }
+
+    #[test]
+    fn test_play_chokudai_search() {
+        let state = generate(5, 5, 10, 0);
+        let final_state = play(&state, |state| chokudai_search(state, 2, 3, 2));
+
+        assert!(final_state.is_done());
+        assert_eq!(final_state.turn, 10);
+    }
+
+    #[test]
+    fn test_play_with_regrowth_and_decay() {
+        // 点が再生するので、取ったマスに戻ってくる価値がある
+        let state = State::parse("@ 5 0 0 0 0 0 3")
+            .unwrap()
+            .with_regrowth(1)
+            .with_end_turn(6);
+        let final_state = play(&state, |state| beam_search(state, 8, 6));
+        assert_eq!(final_state.game_score, 15);
+
+        // 報酬が急に減るなら、遠くの点より近くの点を先に取る
+        let state = State::parse("3 @ 0 0 9")
+            .unwrap()
+            .with_reward(RewardSchedule::Table(vec![1.0, 1.0, 0.0]))
+            .with_end_turn(3);
+        let final_state = play(&state, |state| beam_search(state, 8, 3));
+        assert_eq!(final_state.game_score, 3);
+        assert_eq!(final_state.actions[0], Action::Left);
+    }
+
+    #[test]
+    fn test_play_with_warm_start() {
+        let state = generate(6, 6, 12, 7);
+        let first_plan = beam_search_plan(&state, 1, 12, &[]);
+        let frames = replay(&state, &first_plan);
+        let first_plan_score = frames.last().unwrap().game_score;
+
+        // 前回の計画の残りより悪くはならないので、最初の計画のスコア以上になる
+        let final_state = play_with_plan(&state, true, |state, tail| {
+            beam_search_plan(state, 1, 12, tail)
+        });
+        assert_eq!(final_state.turn, 12);
+        assert!(final_state.game_score >= first_plan_score);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::state::*;
+
+// 全ての行動列を深さ優先で調べて、depth 手先(またはゲーム終了)までで最もスコアの高い状態を返す
+// 小さい迷路で他の探索の答え合わせに使う
+pub fn exhaustive_search(state: &State, depth: usize) -> Option<State> {
+    let mut best = None;
+    dfs(state, depth, 0, &mut best);
+    best
+}
+
+fn dfs(state: &State, depth: usize, t: usize, best: &mut Option<State>) {
+    let legal_actions = state.legal_actions();
+    if t == depth || state.is_done() || legal_actions.is_empty() {
+        if t > 0
+            && best
+                .as_ref()
+                .is_none_or(|best| state.evaluated_score > best.evaluated_score)
+        {
+            *best = Some(state.clone());
+        }
+        return;
+    }
+    for action in legal_actions {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        next_state.evaluate_score();
+        if t == 0 {
+            next_state.first_action = Some(action);
+        }
+        dfs(&next_state, depth, t + 1, best);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::*;
+
+    #[test]
+    fn test_exhaustive_search() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let best = exhaustive_search(&state, 4).unwrap();
+
+        assert_eq!(best.game_score, 20);
+        assert_eq!(best.first_action, Some(Action::Right));
+        assert_eq!(
+            best.actions,
+            vec![Action::Right, Action::Down, Action::Left, Action::Left]
+        );
+    }
+}
@@ -0,0 +1,164 @@
+use crate::action::*;
+use crate::rng::*;
+use crate::state::*;
+
+// 自分の手番は max、湧き(チャンスノード)は確率で重み付けした期待値をとる
+// depth 手先(またはゲーム終了)の game_score の期待値が最大になる手を返す
+pub fn expectimax(state: &State, depth: usize) -> Option<Action> {
+    if depth == 0 {
+        return None;
+    }
+    best_action(state, |next_state| {
+        next_state
+            .spawn_outcomes()
+            .iter()
+            .map(|(probability, outcome)| probability * expectimax_value(outcome, depth - 1))
+            .sum()
+    })
+}
+
+pub fn expectimax_value(state: &State, depth: usize) -> f64 {
+    if depth == 0 || state.is_done() {
+        return state.game_score as f64;
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.game_score as f64;
+    }
+    legal_actions
+        .into_iter()
+        .map(|action| {
+            let mut next_state = state.clone();
+            next_state.advance(action);
+            next_state
+                .spawn_outcomes()
+                .iter()
+                .map(|(probability, outcome)| probability * expectimax_value(outcome, depth - 1))
+                .sum::<f64>()
+        })
+        .fold(f64::MIN, f64::max)
+}
+
+// 湧きの結果を全部列挙する代わりに samples 個だけサンプリングして平均をとる (sparse sampling)
+// 空きマスが多い盤面でも計算量が (行動数 * samples)^depth で抑えられる
+pub fn sampling_search(state: &State, depth: usize, samples: usize, seed: u64) -> Option<Action> {
+    if depth == 0 {
+        return None;
+    }
+    let mut rng = XorShift::new(seed);
+    best_action(state, |next_state| {
+        sampled_chance_value(next_state, depth - 1, samples, &mut rng)
+    })
+}
+
+fn sampled_value(state: &State, depth: usize, samples: usize, rng: &mut XorShift) -> f64 {
+    if depth == 0 || state.is_done() {
+        return state.game_score as f64;
+    }
+    let legal_actions = state.legal_actions();
+    if legal_actions.is_empty() {
+        return state.game_score as f64;
+    }
+    legal_actions
+        .into_iter()
+        .map(|action| {
+            let mut next_state = state.clone();
+            next_state.advance(action);
+            sampled_chance_value(&next_state, depth - 1, samples, rng)
+        })
+        .fold(f64::MIN, f64::max)
+}
+
+fn sampled_chance_value(state: &State, depth: usize, samples: usize, rng: &mut XorShift) -> f64 {
+    let mut total = 0.0;
+    for _ in 0..samples {
+        let mut outcome = state.clone();
+        outcome.spawn_random(rng);
+        total += sampled_value(&outcome, depth, samples, rng);
+    }
+    total / samples as f64
+}
+
+// 1手進めた状態の価値を value で評価して、最も高い手を選ぶ
+fn best_action<F: FnMut(&State) -> f64>(state: &State, mut value: F) -> Option<Action> {
+    let mut best: Option<(f64, Action)> = None;
+    for action in state.legal_actions() {
+        let mut next_state = state.clone();
+        next_state.advance(action);
+        let v = value(&next_state);
+        if best.is_none_or(|(best_value, _)| v > best_value) {
+            best = Some((v, action));
+        }
+    }
+    best.map(|(_, action)| action)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::driver::*;
+    use crate::exhaustive::*;
+    use crate::generator::*;
+
+    #[test]
+    fn test_deterministic_matches_exhaustive() {
+        for seed in 0..5 {
+            let state = generate(3, 3, 4, seed);
+            let optimum = exhaustive_search(&state, 4).unwrap();
+            assert_eq!(expectimax_value(&state, 4), optimum.game_score as f64);
+            assert_eq!(expectimax(&state, 4), optimum.first_action);
+        }
+    }
+
+    #[test]
+    fn test_expected_value() {
+        // 右に動くと、空いているのは左のマスだけなので必ずそこに5点湧く
+        let state = State::parse("@ 0")
+            .unwrap()
+            .with_end_turn(2)
+            .with_spawn(Spawn {
+                probability: 1.0,
+                value: 5,
+            });
+        assert_eq!(expectimax_value(&state, 2), 5.0);
+
+        // 湧く確率が半分なら期待値も半分
+        let state = State::parse("@ 0")
+            .unwrap()
+            .with_end_turn(2)
+            .with_spawn(Spawn {
+                probability: 0.5,
+                value: 5,
+            });
+        assert_eq!(expectimax_value(&state, 2), 2.5);
+    }
+
+    #[test]
+    fn test_prefers_expected_spawns() {
+        // 左は行き止まりで湧く先が少ない。右に行くと空きマスが多い側に居続けられる
+        // どちらも今見えている点は0なので、違いは湧きの期待値だけ
+        let state = State::parse("0 @ 0 0 0")
+            .unwrap()
+            .with_end_turn(3)
+            .with_spawn(Spawn {
+                probability: 1.0,
+                value: 4,
+            });
+        assert_eq!(expectimax(&state, 3), Some(Action::Right));
+        assert_eq!(sampling_search(&state, 3, 16, 0), Some(Action::Right));
+    }
+
+    #[test]
+    fn test_play_stochastic() {
+        let state = generate(4, 4, 8, 0).with_spawn(Spawn {
+            probability: 0.5,
+            value: 9,
+        });
+        let a = play_stochastic(&state, 1, |state| sampling_search(state, 2, 3, 0));
+        let b = play_stochastic(&state, 1, |state| sampling_search(state, 2, 3, 0));
+        assert_eq!(a.turn, 8);
+        // シードが同じなら同じ結果になる
+        assert_eq!(a.actions, b.actions);
+        assert_eq!(a.game_score, b.game_score);
+    }
+}
@@ -0,0 +1,142 @@
+use crate::rng::*;
+use crate::state::*;
+
+// ランダムな迷路を生成する。各マスの点は 0..=9、初期位置もランダム
+pub fn generate(height: usize, width: usize, end_turn: usize, seed: u64) -> State {
+    let mut rng = XorShift::new(seed);
+    let character = Pos::new(rng.gen_range(height), rng.gen_range(width));
+    let mut points = vec![vec![0; width]; height];
+    for row in points.iter_mut() {
+        for point in row.iter_mut() {
+            *point = rng.gen_range(10);
+        }
+    }
+    State::new(character, points).with_end_turn(end_turn)
+}
+
+// wall_ratio の割合でランダムに壁を置く (初期位置には置かない)
+pub fn generate_with_walls(
+    height: usize,
+    width: usize,
+    end_turn: usize,
+    wall_ratio: f64,
+    seed: u64,
+) -> State {
+    let state = generate(height, width, end_turn, seed);
+    let mut rng = XorShift::new(seed.wrapping_add(0x5eed));
+    let mut tiles = vec![vec![Tile::Floor; width]; height];
+    for (y, row) in tiles.iter_mut().enumerate() {
+        for (x, tile) in row.iter_mut().enumerate() {
+            if rng.gen_f64() < wall_ratio && state.character != Pos::new(y, x) {
+                *tile = Tile::Wall;
+            }
+        }
+    }
+    state.with_tiles(tiles)
+}
+
+// シード seed, seed + 1, ... で count 個生成する
+pub fn generate_instances(
+    height: usize,
+    width: usize,
+    end_turn: usize,
+    seed: u64,
+    count: usize,
+) -> Vec<State> {
+    (0..count as u64)
+        .map(|i| generate(height, width, end_turn, seed + i))
+        .collect()
+}
+
+// プロパティテスト用の迷路
+#[cfg(test)]
+pub(crate) mod strategy {
+    use crate::state::*;
+    use proptest::collection::vec;
+    use proptest::prelude::*;
+
+    // 2マス以上 5x5 以下の盤面。walls が true なら初期位置以外に壁も置く
+    pub(crate) fn arb_state(walls: bool) -> impl Strategy<Value = State> {
+        (1..=5usize, 1..=5usize)
+            .prop_filter("at least 2 cells", |&(height, width)| height * width >= 2)
+            .prop_flat_map(move |(height, width)| {
+                let wall_ratio = if walls { 0.2 } else { 0.0 };
+                (
+                    vec(vec(0..=9usize, width), height),
+                    vec(vec(proptest::bool::weighted(wall_ratio), width), height),
+                    0..height,
+                    0..width,
+                    1..=8usize,
+                )
+            })
+            .prop_map(|(points, walls, y, x, end_turn)| {
+                let tiles = walls
+                    .iter()
+                    .enumerate()
+                    .map(|(ty, row)| {
+                        row.iter()
+                            .enumerate()
+                            .map(|(tx, &wall)| {
+                                if wall && (ty, tx) != (y, x) {
+                                    Tile::Wall
+                                } else {
+                                    Tile::Floor
+                                }
+                            })
+                            .collect()
+                    })
+                    .collect();
+                State::new(Pos::new(y, x), points)
+                    .with_end_turn(end_turn)
+                    .with_tiles(tiles)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generate() {
+        let state = generate(5, 7, 10, 1);
+        assert_eq!(state.height(), 5);
+        assert_eq!(state.width(), 7);
+        assert_eq!(state.end_turn, 10);
+        assert!(state.character.y < 5 && state.character.x < 7);
+        assert_eq!(state.points[state.character.y][state.character.x], 0);
+        assert!(state.points.iter().all(|p| p <= 9));
+
+        let same = generate(5, 7, 10, 1);
+        assert_eq!(state.points, same.points);
+        assert_eq!(state.character, same.character);
+    }
+
+    #[test]
+    fn test_generate_with_walls() {
+        let state = generate_with_walls(8, 8, 10, 0.3, 2);
+        let walls = state
+            .rules
+            .tiles
+            .iter()
+            .flatten()
+            .filter(|&&tile| tile == Tile::Wall)
+            .count();
+        assert!(walls > 0 && walls < 64);
+        assert_eq!(state.tile(state.character), Tile::Floor);
+        for y in 0..8 {
+            for x in 0..8 {
+                if state.tile(Pos::new(y, x)) == Tile::Wall {
+                    assert_eq!(state.points[y][x], 0);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_instances() {
+        let instances = generate_instances(3, 4, 4, 0, 5);
+        assert_eq!(instances.len(), 5);
+        assert_ne!(instances[0].points, instances[1].points);
+    }
+}
@@ -0,0 +1,169 @@
+use std::ops::{Index, IndexMut};
+
+// 盤面の点を1本の Vec<u32> に詰めて持つ。grid[y][x] で読み書きできる
+#[derive(Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(try_from = "RawGrid")
+)]
+pub struct Grid {
+    width: usize,
+    cells: Vec<u32>,
+}
+
+// 読み込んだ幅とセル数が合っているか確かめてから Grid にする
+#[cfg(feature = "serde")]
+#[derive(serde::Deserialize)]
+struct RawGrid {
+    width: usize,
+    cells: Vec<u32>,
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<RawGrid> for Grid {
+    type Error = String;
+
+    fn try_from(raw: RawGrid) -> Result<Self, String> {
+        if raw.width == 0 || !raw.cells.len().is_multiple_of(raw.width) {
+            return Err(format!(
+                "{} cells do not fit rows of width {}",
+                raw.cells.len(),
+                raw.width
+            ));
+        }
+        Ok(Grid {
+            width: raw.width,
+            cells: raw.cells,
+        })
+    }
+}
+
+impl Grid {
+    pub fn new(height: usize, width: usize) -> Self {
+        Grid {
+            width,
+            cells: vec![0; height * width],
+        }
+    }
+
+    pub fn from_rows(rows: Vec<Vec<usize>>) -> Self {
+        let width = rows[0].len();
+        let cells = rows
+            .into_iter()
+            .flat_map(|row| {
+                assert_eq!(row.len(), width, "rows must have the same width");
+                row.into_iter().map(to_cell)
+            })
+            .collect();
+        Grid { width, cells }
+    }
+
+    pub fn to_rows(&self) -> Vec<Vec<usize>> {
+        self.cells
+            .chunks(self.width)
+            .map(|row| row.iter().map(|&point| point as usize).collect())
+            .collect()
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len() / self.width
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn get(&self, y: usize, x: usize) -> usize {
+        self.cells[y * self.width + x] as usize
+    }
+
+    pub fn set(&mut self, y: usize, x: usize, point: usize) {
+        self.cells[y * self.width + x] = to_cell(point);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.cells.iter().map(|&point| point as usize)
+    }
+}
+
+// u32 に収まらない点は黙って切り捨てずに panic する
+fn to_cell(point: usize) -> u32 {
+    u32::try_from(point).expect("point must fit in u32")
+}
+
+// clone_from で既存のバッファを使い回せるように手で実装する
+impl Clone for Grid {
+    fn clone(&self) -> Self {
+        Grid {
+            width: self.width,
+            cells: self.cells.clone(),
+        }
+    }
+
+    fn clone_from(&mut self, source: &Self) {
+        self.width = source.width;
+        self.cells.clone_from(&source.cells);
+    }
+}
+
+impl Index<usize> for Grid {
+    type Output = [u32];
+
+    fn index(&self, y: usize) -> &[u32] {
+        &self.cells[y * self.width..(y + 1) * self.width]
+    }
+}
+
+impl IndexMut<usize> for Grid {
+    fn index_mut(&mut self, y: usize) -> &mut [u32] {
+        &mut self.cells[y * self.width..(y + 1) * self.width]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid() {
+        let rows = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let mut grid = Grid::from_rows(rows.clone());
+        assert_eq!(grid.height(), 3);
+        assert_eq!(grid.width(), 4);
+        assert_eq!(grid[1][2], 2);
+        assert_eq!(grid.get(2, 0), 7);
+        assert_eq!(grid.to_rows(), rows);
+
+        grid[1][2] = 0;
+        grid.set(0, 0, 9);
+        assert_eq!(grid.iter().sum::<usize>(), 43);
+    }
+
+    #[test]
+    #[should_panic(expected = "point must fit in u32")]
+    fn test_point_overflow() {
+        Grid::from_rows(vec![vec![u32::MAX as usize + 1]]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_deserialize_checks_shape() {
+        let grid = Grid::from_rows(vec![vec![1, 2], vec![3, 4]]);
+        let json = serde_json::to_string(&grid).unwrap();
+        assert_eq!(serde_json::from_str::<Grid>(&json).unwrap(), grid);
+
+        assert!(serde_json::from_str::<Grid>(r#"{"width":0,"cells":[]}"#).is_err());
+        assert!(serde_json::from_str::<Grid>(r#"{"width":2,"cells":[1,2,3]}"#).is_err());
+    }
+
+    #[test]
+    fn test_clone_from_reuses_buffer() {
+        let source = Grid::from_rows(vec![vec![1, 2], vec![3, 4]]);
+        let mut grid = Grid::new(2, 2);
+        let ptr = grid.cells.as_ptr();
+        grid.clone_from(&source);
+        assert_eq!(grid, source);
+        assert_eq!(grid.cells.as_ptr(), ptr);
+    }
+}
@@ -0,0 +1,236 @@
+use crate::state::*;
+use std::collections::BinaryHeap;
+use std::time::{Duration, Instant};
+// Ref. Furcy, Koenig. Limited Discrepancy Beam Search (IJCAI 2005)
+
+// 普通のビームサーチは各層で上位 beam_width 個(スライス0)だけを残して残りを捨てる。
+// ここでは捨てた候補も beam_width 個ずつのスライスに分けておき、
+// スライス i を選ぶことを i 回の「食い違い」と数えて、合計 max_discrepancy 回まで後戻りして調べる。
+// max_discrepancy = 0 なら beam_search と同じ。十分大きければ全探索になる。
+pub fn limited_discrepancy_beam_search(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    max_discrepancy: usize,
+) -> Option<State> {
+    let mut search = Lds {
+        beam_width,
+        beam_depth,
+        deadline: None,
+        best: None,
+        timed_out: false,
+    };
+    search.probe(vec![state.clone()], 0, max_discrepancy);
+    search.best
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LdsResult {
+    pub best: Option<State>,
+    // 最後に最後まで調べ終えた食い違いの上限
+    pub max_discrepancy: Option<usize>,
+    // true なら全ての候補を調べ終えているので best は最適解
+    pub complete: bool,
+}
+
+// 食い違いの上限を 0, 1, 2, ... と増やしながら time_limit まで探索する
+// 時間が十分あれば全探索に収束して最適解が得られる
+pub fn complete_beam_search(
+    state: &State,
+    beam_width: usize,
+    beam_depth: usize,
+    time_limit: Duration,
+) -> LdsResult {
+    let mut search = Lds {
+        beam_width,
+        beam_depth,
+        deadline: Some(Instant::now() + time_limit),
+        best: None,
+        timed_out: false,
+    };
+    let mut result = LdsResult {
+        best: None,
+        max_discrepancy: None,
+        complete: false,
+    };
+    for max_discrepancy in 0.. {
+        let complete = search.probe(vec![state.clone()], 0, max_discrepancy);
+        if search.timed_out {
+            break;
+        }
+        result.max_discrepancy = Some(max_discrepancy);
+        if complete {
+            result.complete = true;
+            break;
+        }
+    }
+    result.best = search.best;
+    result
+}
+
+struct Lds {
+    beam_width: usize,
+    beam_depth: usize,
+    deadline: Option<Instant>,
+    best: Option<State>,
+    timed_out: bool,
+}
+
+impl Lds {
+    // layer を展開して、選べるスライスを順に再帰的に調べる
+    // 食い違いの上限のせいで調べなかった候補がなければ true
+    fn probe(&mut self, layer: Vec<State>, t: usize, discrepancy: usize) -> bool {
+        let mut next_beam = BinaryHeap::new();
+        for now_state in layer {
+            let legal_actions = now_state.legal_actions();
+            // 1手も進めない状態はそこで終わりなので、exhaustive_search と同じく候補にする
+            if legal_actions.is_empty() && t > 0 {
+                self.update_best(&now_state);
+            }
+            for action in legal_actions {
+                let mut next_state = now_state.clone();
+                next_state.advance(action);
+                next_state.evaluate_score();
+                if t == 0 {
+                    next_state.first_action = Some(action);
+                }
+                next_beam.push(next_state);
+            }
+        }
+
+        let Some(top) = next_beam.peek() else {
+            return true;
+        };
+        if t + 1 == self.beam_depth || top.is_done() {
+            let top = top.clone();
+            self.update_best(&top);
+            return true;
+        }
+
+        let mut complete = true;
+        for i in 0.. {
+            if next_beam.is_empty() {
+                break;
+            }
+            if i > discrepancy {
+                complete = false;
+                break;
+            }
+            if self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+            {
+                self.timed_out = true;
+                return false;
+            }
+            // BinaryHeap から pop する順に切り分けるので、スライス0は beam_search が残すものと一致する
+            let slice = (0..self.beam_width)
+                .map_while(|_| next_beam.pop())
+                .collect::<Vec<State>>();
+            complete &= self.probe(slice, t + 1, discrepancy - i);
+            if self.timed_out {
+                return false;
+            }
+        }
+        complete
+    }
+
+    fn update_best(&mut self, state: &State) {
+        if self
+            .best
+            .as_ref()
+            .is_none_or(|best| state.evaluated_score > best.evaluated_score)
+        {
+            self.best = Some(state.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::*;
+    use crate::beam_search::*;
+    use crate::exhaustive::*;
+    use crate::generator::*;
+    use crate::observer::*;
+
+    #[test]
+    fn test_zero_discrepancy_is_beam_search() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        // 2つ目は右の9を取ると行き止まりになる迷路
+        let dead_end = State::parse("@> 9 #\n1 # #\n1 1 1").unwrap();
+        for state in [State::new(character, points), dead_end] {
+            for beam_width in 1..=4 {
+                let lds = limited_discrepancy_beam_search(&state, beam_width, 4, 0).unwrap();
+                let beam =
+                    beam_search_until(&state, beam_width, 4, None, &mut NoopObserver).unwrap();
+                assert_eq!(lds.evaluated_score, beam.evaluated_score);
+                assert_eq!(lds.first_action, beam.first_action);
+            }
+        }
+    }
+
+    #[test]
+    fn test_discrepancy_improves_width_1() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+
+        let greedy = limited_discrepancy_beam_search(&state, 1, 4, 0).unwrap();
+        assert!(greedy.evaluated_score < 20);
+
+        let result = complete_beam_search(&state, 1, 4, Duration::from_secs(10));
+        assert!(result.complete);
+        let best = result.best.unwrap();
+        assert_eq!(best.evaluated_score, 20);
+        assert_eq!(best.first_action, Some(Action::Right));
+    }
+
+    #[test]
+    fn test_converges_to_exhaustive_optimum() {
+        for seed in 0..10 {
+            let state = generate(3, 3, 5, seed);
+            let optimum = exhaustive_search(&state, 5).unwrap();
+            for beam_width in [1, 2, 3] {
+                let result = complete_beam_search(&state, beam_width, 5, Duration::from_secs(10));
+                assert!(result.complete);
+                assert_eq!(
+                    result.best.unwrap().evaluated_score,
+                    optimum.evaluated_score
+                );
+            }
+        }
+
+        // 1手目の後は行き止まり (戻る先が一方通行)
+        let state = State::parse(
+            "
+            @ 9
+            # #
+            ",
+        )
+        .unwrap()
+        .with_tiles(vec![
+            vec![Tile::OneWay(Action::Right), Tile::Floor],
+            vec![Tile::Wall, Tile::Wall],
+        ]);
+        let optimum = exhaustive_search(&state, 4).unwrap();
+        assert_eq!(optimum.evaluated_score, 9);
+        let lds = limited_discrepancy_beam_search(&state, 1, 4, 0).unwrap();
+        assert_eq!(lds.evaluated_score, 9);
+        assert_eq!(lds.first_action, Some(Action::Right));
+        let result = complete_beam_search(&state, 1, 4, Duration::from_secs(10));
+        assert!(result.complete);
+        assert_eq!(result.best.unwrap().evaluated_score, 9);
+    }
+
+    #[test]
+    fn test_time_limit() {
+        let state = generate(10, 10, 30, 0);
+        let result = complete_beam_search(&state, 2, 30, Duration::ZERO);
+        assert!(!result.complete);
+        assert_eq!(result.max_discrepancy, None);
+    }
+}
@@ -1,3 +1,24 @@
-pub mod state;
+pub mod action;
+pub mod anytime;
 pub mod beam_search;
+pub mod bitboard;
 pub mod chokudai_search;
+pub mod dominance;
+pub mod driver;
+pub mod exhaustive;
+pub mod expectimax;
+pub mod generator;
+pub mod grid;
+pub mod lds_beam_search;
+pub mod multi_start;
+pub mod observer;
+pub mod pathfinding;
+pub mod pool;
+pub mod replay;
+pub mod rng;
+pub mod search_state;
+#[cfg(feature = "serde")]
+pub mod snapshot;
+pub mod state;
+pub mod stats;
+pub mod tuner;
@@ -0,0 +1,247 @@
+use crate::action::*;
+use crate::beam_search::*;
+use crate::observer::*;
+use crate::rng::*;
+use crate::search_state::*;
+use order_by::OrderBy;
+use std::fmt;
+use std::sync::Arc;
+use std::thread;
+
+// 1回分の beam_search の設定
+// 評価値に [0, noise] の乱数を足して並び順を揺らす。乱数は seed と盤面から決まるので同じ設定なら結果も同じ
+// first_actions を指定すると、1手目はその中からしか選ばない
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Start {
+    pub seed: u64,
+    pub noise: usize,
+    pub first_actions: Option<Vec<Action>>,
+}
+
+// シードだけ変えて count 個
+pub fn noisy_starts(count: usize, noise: usize, seed: u64) -> Vec<Start> {
+    (0..count as u64)
+        .map(|i| Start {
+            seed: seed + i,
+            noise,
+            first_actions: None,
+        })
+        .collect()
+}
+
+// 1手目を1つずつに絞って、選べる行動の数だけ
+pub fn first_action_starts<S: SearchState>(state: &S) -> Vec<Start> {
+    state
+        .legal_actions()
+        .into_iter()
+        .map(|action| Start {
+            first_actions: Some(vec![action]),
+            ..Start::default()
+        })
+        .collect()
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiStartResult<S> {
+    // スコアはノイズを含まない本来の評価値
+    pub best: S,
+    // best を見つけたのが starts の何番目か
+    pub start: usize,
+    // starts の順に、それぞれの探索で見つけた状態の評価値
+    pub scores: Vec<usize>,
+}
+
+// starts の設定ごとに beam_search して、評価値が最も高い状態を返す (同点なら starts の前の方)
+// threads が2以上なら starts をスレッドに分けて並列に探索する。結果はスレッド数によらない
+pub fn multi_start_beam_search<S: SearchState + Send + Sync>(
+    state: &S,
+    beam_width: usize,
+    beam_depth: usize,
+    starts: &[Start],
+    threads: usize,
+) -> Option<MultiStartResult<S>> {
+    let run = |start: &Start| {
+        let perturbed = Perturbed::new(state.clone(), start);
+        beam_search_until(&perturbed, beam_width, beam_depth, None, &mut NoopObserver)
+            .unwrap()
+            .inner
+    };
+
+    let threads = threads.clamp(1, starts.len().max(1));
+    let mut results: Vec<Option<S>> = vec![None; starts.len()];
+    if threads == 1 {
+        for (result, start) in results.iter_mut().zip(starts) {
+            *result = Some(run(start));
+        }
+    } else {
+        let chunk_size = starts.len().div_ceil(threads);
+        thread::scope(|scope| {
+            for (results, starts) in results
+                .chunks_mut(chunk_size)
+                .zip(starts.chunks(chunk_size))
+            {
+                let run = &run;
+                scope.spawn(move || {
+                    for (result, start) in results.iter_mut().zip(starts) {
+                        *result = Some(run(start));
+                    }
+                });
+            }
+        });
+    }
+
+    let results = results
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect::<Vec<S>>();
+    let scores = results
+        .iter()
+        .map(|state| state.evaluated_score())
+        .collect::<Vec<usize>>();
+    let start = (0..scores.len()).rev().max_by_key(|&i| scores[i])?;
+    Some(MultiStartResult {
+        best: results.into_iter().nth(start).unwrap(),
+        start,
+        scores,
+    })
+}
+
+// 評価値にノイズを足し、1手目を絞るためのラッパー
+#[derive(Clone, OrderBy)]
+#[order_by(evaluated_score, desc)]
+struct Perturbed<S> {
+    inner: S,
+    seed: u64,
+    noise: usize,
+    first_actions: Option<Arc<Vec<Action>>>,
+    evaluated_score: usize,
+}
+
+impl<S: SearchState> Perturbed<S> {
+    fn new(inner: S, start: &Start) -> Self {
+        let mut perturbed = Perturbed {
+            inner,
+            seed: start.seed,
+            noise: start.noise,
+            first_actions: start.first_actions.clone().map(Arc::new),
+            evaluated_score: 0,
+        };
+        perturbed.evaluate_score();
+        perturbed
+    }
+}
+
+impl<S: SearchState> SearchState for Perturbed<S> {
+    fn legal_actions(&self) -> Vec<Action> {
+        let mut actions = self.inner.legal_actions();
+        // 探索の根 (まだ1手目が決まっていない状態) でだけ絞る
+        if let (None, Some(first_actions)) = (self.inner.first_action(), &self.first_actions) {
+            actions.retain(|action| first_actions.contains(action));
+        }
+        actions
+    }
+
+    fn advance(&mut self, action: Action) {
+        self.inner.advance(action);
+    }
+
+    fn evaluate_score(&mut self) {
+        self.inner.evaluate_score();
+        let noise = if self.noise == 0 {
+            0
+        } else {
+            XorShift::new(self.seed ^ self.inner.hash_key()).gen_range(self.noise + 1)
+        };
+        self.evaluated_score = self.inner.evaluated_score() + noise;
+    }
+
+    fn evaluated_score(&self) -> usize {
+        self.evaluated_score
+    }
+
+    fn first_action(&self) -> Option<Action> {
+        self.inner.first_action()
+    }
+
+    fn set_first_action(&mut self, action: Action) {
+        self.inner.set_first_action(action);
+    }
+
+    fn hash_key(&self) -> u64 {
+        self.inner.hash_key()
+    }
+
+    fn is_done(&self) -> bool {
+        self.inner.is_done()
+    }
+}
+
+impl<S: fmt::Display> fmt::Display for Perturbed<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.inner.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::*;
+    use crate::state::*;
+
+    #[test]
+    fn test_without_noise_is_beam_search() {
+        let state = generate(6, 6, 12, 0);
+        let result = multi_start_beam_search(&state, 4, 12, &[Start::default()], 1).unwrap();
+        let best = beam_search_until(&state, 4, 12, None, &mut NoopObserver).unwrap();
+        assert_eq!(result.best.evaluated_score, best.evaluated_score);
+        assert_eq!(result.best.actions, best.actions);
+        assert_eq!(result.scores, vec![best.evaluated_score]);
+    }
+
+    #[test]
+    fn test_first_action_starts() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+
+        // 幅1だと貪欲に上へ行くが、1手目を変えて回すとより良い下が見つかる
+        let greedy = beam_search_until(&state, 1, 4, None, &mut NoopObserver).unwrap();
+        assert_eq!(greedy.first_action, Some(Action::Up));
+        let starts = first_action_starts(&state);
+        assert_eq!(starts.len(), 4);
+        let result = multi_start_beam_search(&state, 1, 4, &starts, 1).unwrap();
+        // 右, 下, 左, 上の順。同点なら前の方を選ぶ
+        assert_eq!(result.scores, vec![14, 18, 18, 11]);
+        assert_eq!(greedy.evaluated_score, 11);
+        assert_eq!(result.start, 1);
+        assert_eq!(result.best.first_action, Some(Action::Down));
+        for (start, score) in starts.iter().zip(result.scores.iter()) {
+            let only = multi_start_beam_search(&state, 1, 4, std::slice::from_ref(start), 1)
+                .unwrap()
+                .best;
+            assert_eq!(
+                only.first_action,
+                Some(start.first_actions.as_ref().unwrap()[0])
+            );
+            assert_eq!(only.evaluated_score, *score);
+        }
+    }
+
+    #[test]
+    fn test_threads_give_same_result() {
+        let state = generate(8, 8, 16, 0);
+        let starts = noisy_starts(8, 5, 0);
+        let single = multi_start_beam_search(&state, 4, 16, &starts, 1).unwrap();
+        let parallel = multi_start_beam_search(&state, 4, 16, &starts, 3).unwrap();
+        assert_eq!(single.scores, parallel.scores);
+        assert_eq!(single.start, parallel.start);
+        assert_eq!(single.best.actions, parallel.best.actions);
+        assert!(single
+            .scores
+            .iter()
+            .all(|&score| score <= single.best.evaluated_score));
+        assert!(multi_start_beam_search(&state, 4, 16, &[], 2).is_none());
+    }
+}
@@ -0,0 +1,56 @@
+use crate::search_state::*;
+use crate::state::*;
+use std::time::Duration;
+
+// 探索の1層(1ターン分)の展開が終わるたびに通知される情報
+// chokudaiサーチでは pass が何周目か、depth がその周の何層目かを表す
+pub struct LayerInfo<'a, S = State> {
+    pub pass: usize,
+    pub depth: usize,
+    pub best: &'a S,
+    pub beam_size: usize,
+    pub expanded: usize,
+    pub generated: usize,
+    pub elapsed: Duration,
+    // 以下は detailed() が true の observer にだけ集計して渡す
+    // 同じ層で先に生成した子と盤面 (hash_key) が同じ子の数。数えるだけで枝刈りはしない
+    pub duplicate_children: usize,
+    pub scores: &'a [usize],
+}
+
+impl<S: SearchState> LayerInfo<'_, S> {
+    pub fn best_score(&self) -> usize {
+        self.best.evaluated_score()
+    }
+}
+
+pub trait SearchObserver<S = State> {
+    fn on_layer(&mut self, info: &LayerInfo<S>);
+
+    // 重複検出やスコア分布の収集はコストがかかるので、必要な observer だけ true を返す
+    fn detailed(&self) -> bool {
+        false
+    }
+}
+
+// 何もしないデフォルト
+pub struct NoopObserver;
+
+impl<S> SearchObserver<S> for NoopObserver {
+    fn on_layer(&mut self, _info: &LayerInfo<S>) {}
+}
+
+// 以前の beam_search と同じように各層の最良状態を標準出力に出す
+pub struct DebugObserver;
+
+impl<S: SearchState> SearchObserver<S> for DebugObserver {
+    fn on_layer(&mut self, info: &LayerInfo<S>) {
+        print!("{}", info.best);
+    }
+}
+
+impl<S, F: FnMut(&LayerInfo<S>)> SearchObserver<S> for F {
+    fn on_layer(&mut self, info: &LayerInfo<S>) {
+        self(info)
+    }
+}
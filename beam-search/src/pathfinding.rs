@@ -0,0 +1,301 @@
+use std::collections::BinaryHeap;
+use std::collections::HashMap;
+use std::hash::Hash;
+use trait_sort::two_comparison::Point;
+
+// 最短経路問題の定義
+// heuristic はゴールまでの残りコストを過大評価しない(許容的な)値を返すこと
+// 一度展開したノードもより短い経路が見つかれば展開し直すので、無矛盾 (consistent) でなくてもよい
+pub trait ShortestPathProblem {
+    type Node: Clone + Eq + Hash;
+
+    // 隣接ノードと、そこへ移動するコスト
+    fn successors(&self, node: &Self::Node) -> Vec<(Self::Node, usize)>;
+
+    fn heuristic(&self, node: &Self::Node) -> usize;
+
+    fn is_goal(&self, node: &Self::Node) -> bool;
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ShortestPath<N> {
+    // start から goal までのノード列 (両端を含む)
+    pub nodes: Vec<N>,
+    pub cost: usize,
+    // ヒープから取り出して展開したノード数
+    pub expanded: usize,
+}
+
+pub fn dijkstra<S: ShortestPathProblem>(
+    space: &S,
+    start: &S::Node,
+) -> Option<ShortestPath<S::Node>> {
+    best_first_search(space, start, 0.0)
+}
+
+pub fn astar<S: ShortestPathProblem>(space: &S, start: &S::Node) -> Option<ShortestPath<S::Node>> {
+    best_first_search(space, start, 1.0)
+}
+
+// f = g + weight * h。weight > 1 なら最適解の weight 倍以内のコストの経路を、より少ない展開数で探す
+pub fn weighted_astar<S: ShortestPathProblem>(
+    space: &S,
+    start: &S::Node,
+    weight: f64,
+) -> Option<ShortestPath<S::Node>> {
+    best_first_search(space, start, weight)
+}
+
+fn best_first_search<S: ShortestPathProblem>(
+    space: &S,
+    start: &S::Node,
+    weight: f64,
+) -> Option<ShortestPath<S::Node>> {
+    let f = |g: usize, node: &S::Node| {
+        if weight == 0.0 {
+            g
+        } else {
+            g + (weight * space.heuristic(node) as f64).ceil() as usize
+        }
+    };
+
+    // ノードは nodes に登録した順の番号で管理し、Point の id に入れる
+    let mut nodes = vec![start.clone()];
+    let mut index = HashMap::new();
+    index.insert(start.clone(), 0);
+    let mut g = vec![0];
+    let mut parent = vec![None];
+    let mut closed = vec![false];
+
+    // Point は dist が小さいほど先に pop され、dist が同じなら score(= g) が大きいほうが先になる
+    let mut open = BinaryHeap::new();
+    open.push(Point::new(0, f(0, start), 0));
+    let mut expanded = 0;
+
+    while let Some(point) = open.pop() {
+        let id = point.id;
+        // 同じノードを何度も push しているので、古いエントリは読み飛ばす
+        if closed[id] || point.score != g[id] {
+            continue;
+        }
+        closed[id] = true;
+        expanded += 1;
+
+        if space.is_goal(&nodes[id]) {
+            let mut path = vec![];
+            let mut now = Some(id);
+            while let Some(i) = now {
+                path.push(nodes[i].clone());
+                now = parent[i];
+            }
+            path.reverse();
+            return Some(ShortestPath {
+                nodes: path,
+                cost: g[id],
+                expanded,
+            });
+        }
+
+        for (next, cost) in space.successors(&nodes[id]) {
+            let next_g = g[id] + cost;
+            let next_id = match index.get(&next) {
+                Some(&next_id) => {
+                    if next_g >= g[next_id] {
+                        continue;
+                    }
+                    // 展開済みでも、より短い経路が見つかったら開き直す
+                    closed[next_id] = false;
+                    next_id
+                }
+                None => {
+                    nodes.push(next.clone());
+                    g.push(usize::MAX);
+                    parent.push(None);
+                    closed.push(false);
+                    index.insert(next, nodes.len() - 1);
+                    nodes.len() - 1
+                }
+            };
+            g[next_id] = next_g;
+            parent[next_id] = Some(id);
+            open.push(Point::new(next_id, f(next_g, &nodes[next_id]), next_g));
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // '#' は壁、'S' がスタート、'G' がゴール。数字のマスはそのコストで入れる(それ以外は1)
+    struct GridMaze {
+        cells: Vec<Vec<char>>,
+        goal: (usize, usize),
+    }
+
+    impl GridMaze {
+        fn parse(s: &str) -> (Self, (usize, usize)) {
+            let cells = s
+                .lines()
+                .map(|line| line.trim().chars().collect::<Vec<char>>())
+                .filter(|line| !line.is_empty())
+                .collect::<Vec<Vec<char>>>();
+            let find = |c: char| {
+                for (y, row) in cells.iter().enumerate() {
+                    if let Some(x) = row.iter().position(|&cell| cell == c) {
+                        return (y, x);
+                    }
+                }
+                unreachable!();
+            };
+            let start = find('S');
+            let goal = find('G');
+            (GridMaze { cells, goal }, start)
+        }
+    }
+
+    impl ShortestPathProblem for GridMaze {
+        type Node = (usize, usize);
+
+        fn successors(&self, &(y, x): &Self::Node) -> Vec<(Self::Node, usize)> {
+            let mut next = vec![];
+            for (dy, dx) in [(0, 1), (1, 0), (0, !0), (!0, 0)] {
+                let ty = y.wrapping_add(dy);
+                let tx = x.wrapping_add(dx);
+                if ty >= self.cells.len() || tx >= self.cells[ty].len() {
+                    continue;
+                }
+                let cost = match self.cells[ty][tx] {
+                    '#' => continue,
+                    c => c.to_digit(10).map_or(1, |d| d as usize),
+                };
+                next.push(((ty, tx), cost));
+            }
+            next
+        }
+
+        fn heuristic(&self, &(y, x): &Self::Node) -> usize {
+            y.abs_diff(self.goal.0) + x.abs_diff(self.goal.1)
+        }
+
+        fn is_goal(&self, node: &Self::Node) -> bool {
+            *node == self.goal
+        }
+    }
+
+    const MAZE: &str = "
+        S.....#.......
+        .####.#.#####.
+        .#....#.#...#.
+        .#.####.#.#.#.
+        .#......#.#...
+        .########.###.
+        ..........#..G
+    ";
+
+    #[test]
+    fn test_dijkstra_and_astar() {
+        let (maze, start) = GridMaze::parse(MAZE);
+        let dijkstra_path = dijkstra(&maze, &start).unwrap();
+        let astar_path = astar(&maze, &start).unwrap();
+
+        assert_eq!(dijkstra_path.cost, 27);
+        assert_eq!(astar_path.cost, 27);
+        assert_eq!(astar_path.nodes.first(), Some(&start));
+        assert_eq!(astar_path.nodes.last(), Some(&maze.goal));
+        assert_eq!(astar_path.nodes.len(), astar_path.cost + 1);
+        assert!(astar_path.expanded <= dijkstra_path.expanded);
+    }
+
+    #[test]
+    fn test_weighted_cells() {
+        // 真ん中を突っ切るとコスト9のマスを通るので、遠回りのほうが安い
+        let (maze, start) = GridMaze::parse(
+            "
+            S9G
+            ...
+            ",
+        );
+        let path = astar(&maze, &start).unwrap();
+        assert_eq!(path.cost, 4);
+        assert_eq!(path.nodes, vec![(0, 0), (1, 0), (1, 1), (1, 2), (0, 2)]);
+        assert_eq!(dijkstra(&maze, &start).unwrap().cost, 4);
+    }
+
+    #[test]
+    fn test_weighted_astar() {
+        let (maze, start) = GridMaze::parse(
+            "
+            S.............
+            ..............
+            .....#####....
+            .........#....
+            .........#...G
+            ",
+        );
+        let optimal = astar(&maze, &start).unwrap();
+        for weight in [1.5, 2.0, 5.0] {
+            let path = weighted_astar(&maze, &start, weight).unwrap();
+            assert!(path.cost as f64 <= weight * optimal.cost as f64);
+            assert!(path.expanded <= optimal.expanded);
+        }
+    }
+
+    // 隣接リストで書いたグラフ。heuristic は h[node]
+    struct Graph {
+        edges: Vec<Vec<(usize, usize)>>,
+        h: Vec<usize>,
+        goal: usize,
+    }
+
+    impl ShortestPathProblem for Graph {
+        type Node = usize;
+
+        fn successors(&self, &node: &usize) -> Vec<(usize, usize)> {
+            self.edges[node].clone()
+        }
+
+        fn heuristic(&self, &node: &usize) -> usize {
+            self.h[node]
+        }
+
+        fn is_goal(&self, &node: &usize) -> bool {
+            node == self.goal
+        }
+    }
+
+    #[test]
+    fn test_inconsistent_heuristic() {
+        // S=0, A=1, B=2, C=3, G=4。h(A)=4 は許容的だが無矛盾ではないので、
+        // C は先に B 経由 (コスト4) で展開され、後から A 経由 (コスト2) の経路が見つかる
+        let graph = Graph {
+            edges: vec![
+                vec![(1, 1), (2, 3)],
+                vec![(3, 1)],
+                vec![(3, 1)],
+                vec![(4, 3)],
+                vec![],
+            ],
+            h: vec![0, 4, 0, 0, 0],
+            goal: 4,
+        };
+        let path = astar(&graph, &0).unwrap();
+        assert_eq!(path.cost, 5);
+        assert_eq!(path.nodes, vec![0, 1, 3, 4]);
+        assert_eq!(dijkstra(&graph, &0).unwrap().cost, 5);
+    }
+
+    #[test]
+    fn test_unreachable() {
+        let (maze, start) = GridMaze::parse(
+            "
+            S.#.
+            ..#G
+            ",
+        );
+        assert!(astar(&maze, &start).is_none());
+        assert!(dijkstra(&maze, &start).is_none());
+    }
+}
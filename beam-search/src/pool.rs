@@ -0,0 +1,102 @@
+use crate::action::*;
+use crate::search_state::*;
+use crate::state::*;
+
+// 探索で捨てた State を取っておき、次に State を複製するときに clone_from で中身だけ上書きして使い回す
+// 盤面などのバッファを確保し直さずに済む
+pub struct StatePool<S = State> {
+    free: Vec<S>,
+    capacity: usize,
+    // 新しく確保した回数と使い回した回数
+    pub allocated: usize,
+    pub reused: usize,
+}
+
+impl<S: Clone> StatePool<S> {
+    // capacity 個までしか取っておかない。0 なら使い回しをしない (cargo bench --bench allocations での比較用)
+    pub fn new(capacity: usize) -> Self {
+        StatePool {
+            free: vec![],
+            capacity,
+            allocated: 0,
+            reused: 0,
+        }
+    }
+
+    pub fn clone_state(&mut self, source: &S) -> S {
+        match self.free.pop() {
+            Some(mut state) => {
+                state.clone_from(source);
+                self.reused += 1;
+                state
+            }
+            None => {
+                self.allocated += 1;
+                source.clone()
+            }
+        }
+    }
+
+    pub fn recycle(&mut self, state: S) {
+        if self.free.len() < self.capacity {
+            self.free.push(state);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.free.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.free.is_empty()
+    }
+}
+
+// 探索で作る pool の容量。1層分の子 (beam_width × 分岐数) を取っておければ、捨てた状態を全部使い回せる
+// 分岐数は state の合法手の数だが、角や壁際で少なく見積もらないように上下左右の4以上にする
+pub fn beam_capacity<S: SearchState>(state: &S, beam_width: usize) -> usize {
+    let branching = state.legal_actions().len().max(Action::MOVES.len());
+    beam_width.saturating_mul(branching)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let mut pool = StatePool::new(1);
+
+        let a = pool.clone_state(&state);
+        let b = pool.clone_state(&state);
+        assert_eq!((pool.allocated, pool.reused), (2, 0));
+
+        pool.recycle(a);
+        pool.recycle(b);
+        assert_eq!(pool.len(), 1);
+
+        let c = pool.clone_state(&state);
+        assert_eq!((pool.allocated, pool.reused), (2, 1));
+        assert_eq!(c.points, state.points);
+        assert!(pool.is_empty());
+    }
+
+    #[test]
+    fn test_beam_capacity() {
+        let state = State::parse("@ 1\n2 3").unwrap();
+        assert_eq!(beam_capacity(&state, 10), 40);
+        let state = state.with_actions(vec![
+            Action::Right,
+            Action::Down,
+            Action::Left,
+            Action::Up,
+            Action::Stay,
+        ]);
+        assert_eq!(beam_capacity(&state, 10), 40);
+        let state = state.with_custom_action(0, 1).with_custom_action(1, 1);
+        assert_eq!(beam_capacity(&state, 10), 50);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::action::*;
+use crate::state::*;
+use std::fmt::Write as _;
+use std::io;
+use std::thread;
+use std::time::Duration;
+
+const CELL_SIZE: usize = 40;
+
+// 初期状態から行動列を順に適用して、各ターンの盤面をフレームとして返す
+// フレーム数は actions.len() + 1 (先頭は初期状態)
+pub fn replay(state: &State, actions: &[Action]) -> Vec<State> {
+    let mut frames = vec![state.clone()];
+    let mut now_state = state.clone();
+    for &action in actions {
+        assert!(
+            now_state.legal_actions().contains(&action),
+            "illegal action {:?} at turn {}",
+            action,
+            now_state.turn
+        );
+        now_state.advance(action);
+        now_state.evaluate_score();
+        frames.push(now_state.clone());
+    }
+    frames
+}
+
+pub fn render_ascii(frames: &[State]) -> String {
+    let mut out = String::new();
+    for frame in frames {
+        write!(out, "{}", frame).unwrap();
+        out.push('\n');
+    }
+    out
+}
+
+// ターミナル上でフレームを上書きしながら表示する
+pub fn animate_ascii<W: io::Write>(
+    out: &mut W,
+    frames: &[State],
+    delay: Duration,
+) -> io::Result<()> {
+    for frame in frames {
+        // 画面クリア + カーソルを左上へ
+        write!(out, "\x1b[2J\x1b[H{}", frame)?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+pub fn render_svg(state: &State) -> String {
+    let height = state.height();
+    let width = state.width();
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}">"#,
+        width * CELL_SIZE,
+        height * CELL_SIZE
+    )
+    .unwrap();
+    for y in 0..height {
+        for x in 0..width {
+            let (px, py) = (x * CELL_SIZE, y * CELL_SIZE);
+            let point = state.points.get(y, x);
+            let tile = state.tile(Pos::new(y, x));
+            let (fill, label) = match tile {
+                Tile::Wall => ("dimgray", None),
+                Tile::OneWay(direction) => {
+                    let arrow = match direction {
+                        Action::Right => "→",
+                        Action::Down => "↓",
+                        Action::Left => "←",
+                        Action::Up => "↑",
+                        _ => unreachable!("with_tiles rejects other one-way directions"),
+                    };
+                    ("whitesmoke", Some(arrow.to_string()))
+                }
+                Tile::Teleporter { label, .. } => ("lightblue", Some(label.to_string())),
+                Tile::Floor if point > 0 => ("lightyellow", Some(point.to_string())),
+                Tile::Floor => ("whitesmoke", None),
+            };
+            writeln!(
+                svg,
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="gray"/>"#,
+                px, py, CELL_SIZE, CELL_SIZE, fill
+            )
+            .unwrap();
+            let (cx, cy) = (px + CELL_SIZE / 2, py + CELL_SIZE / 2);
+            if state.character.y == y && state.character.x == x {
+                writeln!(
+                    svg,
+                    r#"<circle cx="{}" cy="{}" r="{}" fill="crimson"/>"#,
+                    cx,
+                    cy,
+                    CELL_SIZE / 3
+                )
+                .unwrap();
+            } else if let Some(label) = label {
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    cx, cy, label
+                )
+                .unwrap();
+            }
+        }
+    }
+    svg.push_str("</svg>");
+    svg
+}
+
+// ブラウザで開ける単体のHTMLを生成する。スライダーと前後ボタンでターンを切り替える
+pub fn render_html(frames: &[State]) -> String {
+    let mut html = String::new();
+    html.push_str(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>replay</title>
+<style>
+body { font-family: sans-serif; }
+.frame { display: none; }
+.frame.active { display: block; }
+</style>
+</head>
+<body>
+"#,
+    );
+    writeln!(
+        html,
+        r#"<div><button id="prev">&lt;</button> <input id="turn" type="range" min="0" max="{}" value="0"> <button id="next">&gt;</button></div>"#,
+        frames.len().saturating_sub(1)
+    )
+    .unwrap();
+    for (i, frame) in frames.iter().enumerate() {
+        let class = if i == 0 { "frame active" } else { "frame" };
+        writeln!(html, r#"<div class="{}">"#, class).unwrap();
+        writeln!(
+            html,
+            "<p>turn {} / score {}</p>",
+            frame.turn, frame.game_score
+        )
+        .unwrap();
+        writeln!(html, "{}", render_svg(frame)).unwrap();
+        html.push_str("</div>\n");
+    }
+    html.push_str(
+        r#"<script>
+const frames = document.querySelectorAll(".frame");
+const slider = document.getElementById("turn");
+function show(i) {
+  frames.forEach((f, j) => f.classList.toggle("active", i === j));
+  slider.value = i;
+}
+slider.addEventListener("input", () => show(Number(slider.value)));
+document.getElementById("prev").addEventListener("click", () => show(Math.max(0, Number(slider.value) - 1)));
+document.getElementById("next").addEventListener("click", () => show(Math.min(frames.length - 1, Number(slider.value) + 1)));
+</script>
+</body>
+</html>
+"#,
+    );
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let frames = replay(&state, &[Action::Right, Action::Down]);
+
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].turn, 0);
+        assert_eq!(frames[1].game_score, 2);
+        assert_eq!(frames[2].game_score, 8);
+        assert_eq!(frames[2].character.y, 2);
+        assert_eq!(frames[2].character.x, 2);
+    }
+
+    #[test]
+    fn test_render_ascii() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let frames = replay(&state, &[Action::Right]);
+        let ascii = render_ascii(&frames);
+
+        let expect = "# turn 0\nscore 0\n4 6 1 3 \n0 @ 2 0 \n7 5 6 6 \n\n\
+                      # turn 1\nscore 2\n4 6 1 3 \n0 0 @ 0 \n7 5 6 6 \n\n";
+        assert_eq!(ascii, expect);
+    }
+
+    #[test]
+    fn test_render_html() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let frames = replay(
+            &state,
+            &[Action::Right, Action::Down, Action::Left, Action::Up],
+        );
+        let html = render_html(&frames);
+
+        assert_eq!(html.matches("<svg").count(), 5);
+        assert_eq!(html.matches("<circle").count(), 5);
+        assert!(html.contains(r#"max="4""#));
+    }
+
+    #[test]
+    fn test_render_svg_tiles() {
+        let state = State::parse(
+            "
+            @ # a
+            > 3 a
+            ",
+        )
+        .unwrap();
+        let svg = render_svg(&state);
+
+        assert_eq!(svg.matches("dimgray").count(), 1);
+        assert_eq!(svg.matches("lightblue").count(), 2);
+        assert!(svg.contains("→"));
+        assert!(svg.contains(">3</text>"));
+    }
+}
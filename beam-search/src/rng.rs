@@ -0,0 +1,66 @@
+// 外部クレートに頼らない、シード固定で再現できる乱数 (xorshift64)
+#[derive(Clone, Debug)]
+pub struct XorShift {
+    state: u64,
+}
+
+impl XorShift {
+    pub fn new(seed: u64) -> Self {
+        const MIX: u64 = 0x9e37_79b9_7f4a_7c15;
+        // 状態が0だと0しか出なくなるので、seed == MIX のときだけ別の値にする
+        let state = match seed ^ MIX {
+            0 => MIX,
+            state => state,
+        };
+        XorShift { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.state = x;
+        x
+    }
+
+    // [0, n) の一様乱数
+    pub fn gen_range(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    // [0, 1) の一様乱数
+    pub fn gen_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_same_seed_same_sequence() {
+        let mut a = XorShift::new(42);
+        let mut b = XorShift::new(42);
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+    }
+
+    #[test]
+    fn test_no_zero_state() {
+        let mut rng = XorShift::new(0x9e37_79b9_7f4a_7c15);
+        assert!((0..10).any(|_| rng.next_u64() != 0));
+    }
+
+    #[test]
+    fn test_gen_range() {
+        let mut rng = XorShift::new(0);
+        for _ in 0..1000 {
+            assert!(rng.gen_range(10) < 10);
+            let f = rng.gen_f64();
+            assert!((0.0..1.0).contains(&f));
+        }
+    }
+}
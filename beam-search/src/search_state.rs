@@ -0,0 +1,52 @@
+use crate::action::*;
+use crate::state::*;
+use std::fmt::Display;
+
+// 探索アルゴリズムが状態に求める操作。State と bitboard::BitState が実装する
+// BinaryHeap に積むので evaluated_score で順序づける
+pub trait SearchState: Clone + Ord + Display {
+    fn legal_actions(&self) -> Vec<Action>;
+    fn advance(&mut self, action: Action);
+    fn evaluate_score(&mut self);
+    fn evaluated_score(&self) -> usize;
+    // 探索開始から数えて1手目の行動
+    fn first_action(&self) -> Option<Action>;
+    fn set_first_action(&mut self, action: Action);
+    // 重複検出に使う。盤面が同じ状態は同じ値になる
+    fn hash_key(&self) -> u64;
+    fn is_done(&self) -> bool;
+}
+
+impl SearchState for State {
+    fn legal_actions(&self) -> Vec<Action> {
+        State::legal_actions(self)
+    }
+
+    fn advance(&mut self, action: Action) {
+        State::advance(self, action)
+    }
+
+    fn evaluate_score(&mut self) {
+        State::evaluate_score(self)
+    }
+
+    fn evaluated_score(&self) -> usize {
+        self.evaluated_score
+    }
+
+    fn first_action(&self) -> Option<Action> {
+        self.first_action
+    }
+
+    fn set_first_action(&mut self, action: Action) {
+        self.first_action = Some(action);
+    }
+
+    fn hash_key(&self) -> u64 {
+        State::hash_key(self)
+    }
+
+    fn is_done(&self) -> bool {
+        State::is_done(self)
+    }
+}
@@ -0,0 +1,121 @@
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::Path;
+
+// 途中の State や chokudai_search_beams のビームを保存しておき、後で読み込んで悪手の原因を調べる
+// 読みやすい JSON と、小さいバイナリ (bincode) の2通り
+// State の rules は Arc で共有しているが、保存すると状態ごとに別々に復元される
+
+pub fn to_json<T: Serialize>(value: &T) -> Result<String, String> {
+    serde_json::to_string(value).map_err(|e| e.to_string())
+}
+
+pub fn from_json<T: DeserializeOwned>(s: &str) -> Result<T, String> {
+    serde_json::from_str(s).map_err(|e| e.to_string())
+}
+
+pub fn to_binary<T: Serialize>(value: &T) -> Result<Vec<u8>, String> {
+    bincode::serialize(value).map_err(|e| e.to_string())
+}
+
+pub fn from_binary<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    bincode::deserialize(bytes).map_err(|e| e.to_string())
+}
+
+// 拡張子が .json なら JSON、それ以外はバイナリで書き出す
+pub fn save<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    let bytes = if is_json(path) {
+        to_json(value)?.into_bytes()
+    } else {
+        to_binary(value)?
+    };
+    fs::write(path, bytes).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+pub fn load<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let bytes = fs::read(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    if is_json(path) {
+        from_json(std::str::from_utf8(&bytes).map_err(|e| e.to_string())?)
+    } else {
+        from_binary(&bytes)
+    }
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "json")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action::*;
+    use crate::chokudai_search::*;
+    use crate::generator::*;
+    use crate::multi_start::*;
+    use crate::state::*;
+
+    #[test]
+    fn test_state_round_trip() {
+        let mut state = State::parse(
+            "
+            @ # a
+            > 3 a
+            ",
+        )
+        .unwrap()
+        .with_regrowth(2);
+        state.advance(Action::Down);
+        state.evaluate_score();
+
+        let json = to_json(&state).unwrap();
+        let binary = to_binary(&state).unwrap();
+        assert!(binary.len() < json.len());
+
+        for restored in [
+            from_json::<State>(&json).unwrap(),
+            from_binary::<State>(&binary).unwrap(),
+        ] {
+            assert_eq!(restored.to_string(), state.to_string());
+            assert_eq!(restored.actions, state.actions);
+            assert_eq!(restored.legal_actions(), state.legal_actions());
+            assert_eq!(restored.hash_key(), state.hash_key());
+        }
+        assert!(from_json::<State>("{}").is_err());
+    }
+
+    #[test]
+    fn test_multi_start_round_trip() {
+        let state = generate(5, 5, 6, 0);
+        let starts = noisy_starts(3, 5, 0);
+        let result = multi_start_beam_search(&state, 4, 6, &starts, 1).unwrap();
+
+        let restored: MultiStartResult<State> = from_json(&to_json(&result).unwrap()).unwrap();
+        assert_eq!(restored.scores, result.scores);
+        assert_eq!(restored.start, result.start);
+        assert_eq!(restored.best.actions, result.best.actions);
+        let restored: Vec<Start> = from_binary(&to_binary(&starts).unwrap()).unwrap();
+        assert_eq!(restored.len(), 3);
+        assert_eq!(restored[2].seed, 2);
+    }
+
+    #[test]
+    fn test_resume_chokudai_search() {
+        let state = generate(6, 6, 12, 0);
+        let expect = chokudai_search(&state, 3, 12, 4);
+
+        let dir = std::env::temp_dir();
+        for name in ["chokudai_beams.json", "chokudai_beams.bin"] {
+            // 同時に走る別の cargo test とぶつからないようにプロセスIDを付ける
+            let path = dir.join(format!("{}_{}", std::process::id(), name));
+            save(&path, &chokudai_search_beams(&state, 3, 12, 2)).unwrap();
+            let mut beams: ChokudaiBeams = load(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(
+                chokudai_search_resume(&state, &mut beams, 3, 2).unwrap(),
+                expect
+            );
+        }
+    }
+}
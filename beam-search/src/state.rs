@@ -1,12 +1,17 @@
 #![allow(unused)]
+use crate::action::*;
+use crate::grid::*;
+use crate::rng::*;
+use order_by::OrderBy;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
 
-const END_TURN: usize = 4;
-const DX: [usize; 4] = [1, 0, !0, 0];
-const DY: [usize; 4] = [0, 1, 0, !0];
-const HEIGHT: usize = 3;
-const WIDTH: usize = 4;
+pub const END_TURN: usize = 4;
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pos {
     pub y: usize,
     pub x: usize,
@@ -18,119 +23,500 @@ impl Pos {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Tile {
+    Floor,
+    Wall,
+    // 指定の向き(action)に移動するときだけ入れる
+    OneWay(Action),
+    // 入ると to へ飛ぶ。label は同じ文字同士がペアになる
+    Teleporter { label: char, to: Pos },
+}
+
+// 何ターン目に取ったかで得点にかける倍率 (ターンは0始まり)
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RewardSchedule {
+    Constant,
+    // turn ターン目は rate^turn 倍
+    Decay(f64),
+    // turn ターン目は table[turn] 倍。表より後のターンは最後の値
+    Table(Vec<f64>),
+}
+
+impl RewardSchedule {
+    pub fn multiplier(&self, turn: usize) -> f64 {
+        match self {
+            RewardSchedule::Constant => 1.0,
+            RewardSchedule::Decay(rate) => rate.powi(turn as i32),
+            RewardSchedule::Table(table) => {
+                table.get(turn).or(table.last()).copied().unwrap_or(1.0)
+            }
+        }
+    }
+
+    pub fn reward(&self, point: usize, turn: usize) -> usize {
+        match self {
+            RewardSchedule::Constant => point,
+            _ => (point as f64 * self.multiplier(turn)).round() as usize,
+        }
+    }
+}
+
+// 毎ターン、行動の後に probability の確率で空いているマス1つ(一様に選ぶ)に value 点が湧く
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Spawn {
+    pub probability: f64,
+    pub value: usize,
+}
+
+// 盤面の地形など、ゲーム中に変わらないルール。全ての状態で共有する
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Rules {
+    pub tiles: Vec<Vec<Tile>>,
+    // 選べる行動。legal_actions はこの順に返す
+    pub actions: Vec<Action>,
+    // Action::Custom(i) の移動量 (dy, dx)
+    pub custom_moves: Vec<(isize, isize)>,
+    // 取ったマスがこのターン数だけ経つと元の点に戻る。None なら戻らない
+    pub regrowth: Option<usize>,
+    pub reward: RewardSchedule,
+    // advance は決定的なままで、湧きは spawn_outcomes / spawn_random で別に適用する
+    pub spawn: Option<Spawn>,
+}
+
+// BinaryHeap でスコアが高いものから取り出す
+#[derive(Debug, OrderBy)]
+#[order_by(evaluated_score, desc)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct State {
-    pub first_action: Option<usize>,
+    pub first_action: Option<Action>,
+    // ゲーム開始からの行動履歴
+    pub actions: Vec<Action>,
     pub character: Pos,
-    pub points: Vec<Vec<usize>>,
+    pub points: Grid,
     pub turn: usize,
+    pub end_turn: usize,
     pub game_score: usize,
     pub evaluated_score: usize,
+    // 再生待ちのマス (再生するターン, 位置, 点)
+    pub regrowing: Vec<(usize, Pos, usize)>,
+    pub rules: Arc<Rules>,
 }
 
 impl State {
     pub fn new(character: Pos, mut points: Vec<Vec<usize>>) -> Self {
         points[character.y][character.x] = 0;
+        let tiles = vec![vec![Tile::Floor; points[0].len()]; points.len()];
         State {
             first_action: None,
+            actions: vec![],
             character,
-            points,
+            points: Grid::from_rows(points),
             turn: 0,
+            end_turn: END_TURN,
             game_score: 0,
             evaluated_score: 0,
+            regrowing: vec![],
+            rules: Arc::new(Rules {
+                tiles,
+                actions: Action::MOVES.to_vec(),
+                custom_moves: vec![],
+                regrowth: None,
+                reward: RewardSchedule::Constant,
+                spawn: None,
+            }),
         }
     }
 
-    pub fn advance(&mut self, action: usize) {
-        let ty = self.character.y.wrapping_add(DY[action]);
-        let tx = self.character.x.wrapping_add(DX[action]);
+    pub fn with_actions(mut self, actions: Vec<Action>) -> Self {
+        Arc::make_mut(&mut self.rules).actions = actions;
+        self
+    }
+
+    pub fn with_regrowth(mut self, delay: usize) -> Self {
+        Arc::make_mut(&mut self.rules).regrowth = Some(delay);
+        self
+    }
 
-        self.character.y = ty;
-        self.character.x = tx;
-        let point = self.points[ty][tx];
+    pub fn with_reward(mut self, reward: RewardSchedule) -> Self {
+        Arc::make_mut(&mut self.rules).reward = reward;
+        self
+    }
+
+    pub fn with_spawn(mut self, spawn: Spawn) -> Self {
+        Arc::make_mut(&mut self.rules).spawn = Some(spawn);
+        self
+    }
+
+    // 移動量 (dy, dx) の行動を Action::Custom として追加する
+    pub fn with_custom_action(mut self, dy: isize, dx: isize) -> Self {
+        let rules = Arc::make_mut(&mut self.rules);
+        rules.custom_moves.push((dy, dx));
+        rules
+            .actions
+            .push(Action::Custom(rules.custom_moves.len() - 1));
+        self
+    }
+
+    // 壁のマスの点は0にする
+    // 一方通行の向きは上下左右だけ、テレポーターのラベルは v 以外の英小文字だけ
+    // (Display の出力を parse で読み戻せるように)
+    pub fn with_tiles(mut self, tiles: Vec<Vec<Tile>>) -> Self {
+        for (y, row) in tiles.iter().enumerate() {
+            for (x, tile) in row.iter().enumerate() {
+                match *tile {
+                    Tile::Wall => self.points.set(y, x, 0),
+                    Tile::OneWay(direction) => assert!(
+                        direction.arrow().is_some(),
+                        "one-way tile at ({}, {}) must point up, down, left or right",
+                        y,
+                        x
+                    ),
+                    // v は下向きの一方通行と区別できないので使えない
+                    Tile::Teleporter { label, .. } => assert!(
+                        label.is_ascii_lowercase() && label != 'v',
+                        "teleporter label at ({}, {}) must be a lowercase letter other than v",
+                        y,
+                        x
+                    ),
+                    _ => {}
+                }
+            }
+        }
+        Arc::make_mut(&mut self.rules).tiles = tiles;
+        self
+    }
+
+    // 空白区切りで1行ずつ盤面を読む。Display の出力もそのまま読める
+    //   数字: そのマスの点 / @: 初期位置 / #: 壁
+    //   > v < ^: その向きに移動するときだけ入れるマス
+    //   v 以外の英小文字: テレポーター。同じ文字の2マスが互いに行き来する
+    //   @ の直後にはキャラクターがいるマスの地形を書ける (@a, @> など)
+    pub fn parse(s: &str) -> Result<State, String> {
+        let mut character = None;
+        let mut points = vec![];
+        let mut tiles = vec![];
+        let mut teleporters: HashMap<char, Vec<Pos>> = HashMap::new();
+        let rows = s.lines().map(|line| line.trim()).filter(|line| {
+            !line.is_empty() && !line.starts_with("# turn") && !line.starts_with("score")
+        });
+        for (y, line) in rows.enumerate() {
+            let mut point_row = vec![];
+            let mut tile_row = vec![];
+            for (x, token) in line.split_whitespace().enumerate() {
+                let token = match token.strip_prefix('@') {
+                    Some(under) => {
+                        if character.is_some() {
+                            return Err(format!("multiple start positions at ({}, {})", y, x));
+                        }
+                        if under == "#" {
+                            return Err(format!("start position on a wall at ({}, {})", y, x));
+                        }
+                        character = Some(Pos::new(y, x));
+                        // キャラクターのいるマスの点は0になる
+                        if under.is_empty() {
+                            "0"
+                        } else {
+                            under
+                        }
+                    }
+                    None => token,
+                };
+                let tile = match token {
+                    "#" => Tile::Wall,
+                    ">" => Tile::OneWay(Action::Right),
+                    "v" => Tile::OneWay(Action::Down),
+                    "<" => Tile::OneWay(Action::Left),
+                    "^" => Tile::OneWay(Action::Up),
+                    _ => {
+                        let mut chars = token.chars();
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) if c.is_ascii_lowercase() => {
+                                teleporters.entry(c).or_default().push(Pos::new(y, x));
+                                // 行き先はペアがそろってから埋める
+                                Tile::Teleporter {
+                                    label: c,
+                                    to: Pos::new(y, x),
+                                }
+                            }
+                            _ => {
+                                let point = token.parse::<usize>().map_err(|_| {
+                                    format!("unknown token {:?} at ({}, {})", token, y, x)
+                                })?;
+                                point_row.push(point);
+                                tile_row.push(Tile::Floor);
+                                continue;
+                            }
+                        }
+                    }
+                };
+                point_row.push(0);
+                tile_row.push(tile);
+            }
+            if let Some(first) = points.first() {
+                if Vec::len(first) != point_row.len() {
+                    return Err(format!("row {} has a different width", y));
+                }
+            }
+            points.push(point_row);
+            tiles.push(tile_row);
+        }
+
+        let character = character.ok_or("no start position (@)")?;
+        for (label, pads) in teleporters {
+            let [a, b] = pads[..] else {
+                return Err(format!("teleporter {:?} must appear exactly twice", label));
+            };
+            tiles[a.y][a.x] = Tile::Teleporter { label, to: b };
+            tiles[b.y][b.x] = Tile::Teleporter { label, to: a };
+        }
+        Ok(State::new(character, points).with_tiles(tiles))
+    }
+
+    pub fn with_end_turn(mut self, end_turn: usize) -> Self {
+        self.end_turn = end_turn;
+        self
+    }
+
+    // 盤面の大きさは points から決まる
+    pub fn height(&self) -> usize {
+        self.points.height()
+    }
+
+    pub fn width(&self) -> usize {
+        self.points.width()
+    }
+
+    pub fn tile(&self, pos: Pos) -> Tile {
+        self.rules.tiles[pos.y][pos.x]
+    }
+
+    // 行動後の位置。盤面の外か、移動量が登録されていない Custom なら None
+    fn destination(&self, action: Action) -> Option<Pos> {
+        let (dy, dx) = match action {
+            Action::Custom(i) => *self.rules.custom_moves.get(i)?,
+            _ => action.delta().unwrap(),
+        };
+        let ty = self.character.y.checked_add_signed(dy)?;
+        let tx = self.character.x.checked_add_signed(dx)?;
+        if self.height() <= ty || self.width() <= tx {
+            return None;
+        }
+        Some(Pos::new(ty, tx))
+    }
+
+    pub fn advance(&mut self, action: Action) {
+        let target = self.destination(action).unwrap();
+        // テレポーター上で Stay しても飛ばない
+        self.character = match self.tile(target) {
+            Tile::Teleporter { to, .. } if target != self.character => to,
+            _ => target,
+        };
+        let (ty, tx) = (self.character.y, self.character.x);
+        let point = self.points.get(ty, tx);
         if point > 0 {
-            self.game_score += point;
-            self.points[ty][tx] = 0;
+            self.game_score += self.rules.reward.reward(point, self.turn);
+            self.points.set(ty, tx, 0);
+            if let Some(delay) = self.rules.regrowth {
+                self.regrowing
+                    .push((self.turn + 1 + delay, self.character, point));
+            }
         }
         self.turn += 1;
+        self.actions.push(action);
+
+        if !self.regrowing.is_empty() {
+            let turn = self.turn;
+            let points = &mut self.points;
+            self.regrowing.retain(|&(regrow_turn, pos, point)| {
+                if regrow_turn <= turn {
+                    points.set(pos.y, pos.x, point);
+                    false
+                } else {
+                    true
+                }
+            });
+        }
     }
 
-    pub fn legal_actions(&self) -> Vec<usize> {
+    pub fn legal_actions(&self) -> Vec<Action> {
         let mut actions = vec![];
-        for action in 0..DX.len() {
-            let ty = self.character.y.wrapping_add(DY[action]);
-            let tx = self.character.x.wrapping_add(DX[action]);
-
-            if HEIGHT <= ty || WIDTH <= tx {
+        for &action in self.rules.actions.iter() {
+            let Some(to) = self.destination(action) else {
                 continue;
+            };
+            // その場に留まるだけなら地形は関係ない
+            if to != self.character {
+                match self.tile(to) {
+                    Tile::Wall => continue,
+                    Tile::OneWay(direction) if direction != action => continue,
+                    _ => {}
+                }
             }
             actions.push(action);
         }
         actions
     }
 
+    // 点が湧く候補のマス (床で点が0、キャラクターがいない)
+    fn spawn_cells(&self) -> Vec<Pos> {
+        let mut cells = vec![];
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let pos = Pos::new(y, x);
+                if self.points.get(y, x) == 0
+                    && pos != self.character
+                    && self.tile(pos) == Tile::Floor
+                {
+                    cells.push(pos);
+                }
+            }
+        }
+        cells
+    }
+
+    // 湧きで起こりうる全ての結果と、その確率
+    pub fn spawn_outcomes(&self) -> Vec<(f64, State)> {
+        let cells = self.spawn_cells();
+        let spawn = match self.rules.spawn {
+            Some(spawn) if spawn.probability > 0.0 && !cells.is_empty() => spawn,
+            _ => return vec![(1.0, self.clone())],
+        };
+        let mut outcomes = vec![];
+        if spawn.probability < 1.0 {
+            outcomes.push((1.0 - spawn.probability, self.clone()));
+        }
+        let probability = spawn.probability / cells.len() as f64;
+        for pos in cells {
+            let mut next_state = self.clone();
+            next_state.points.set(pos.y, pos.x, spawn.value);
+            outcomes.push((probability, next_state));
+        }
+        outcomes
+    }
+
+    pub fn spawn_random(&mut self, rng: &mut XorShift) {
+        let Some(spawn) = self.rules.spawn else {
+            return;
+        };
+        if rng.gen_f64() >= spawn.probability {
+            return;
+        }
+        let cells = self.spawn_cells();
+        if cells.is_empty() {
+            return;
+        }
+        let pos = cells[rng.gen_range(cells.len())];
+        self.points.set(pos.y, pos.x, spawn.value);
+    }
+
     pub fn evaluate_score(&mut self) {
         self.evaluated_score = self.game_score;
     }
 
+    // 盤面(位置・残りの点・ターン)が同じ状態は同じ値になる
+    pub fn hash_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.character.hash(&mut hasher);
+        self.points.hash(&mut hasher);
+        self.turn.hash(&mut hasher);
+        self.regrowing.hash(&mut hasher);
+        hasher.finish()
+    }
+
     pub fn is_done(&self) -> bool {
-        self.turn >= END_TURN
+        self.turn >= self.end_turn
     }
 
     pub fn debug(&self) {
-        println!("# turn {}", self.turn);
-        println!("score {}", self.evaluated_score);
-        for y in 0..HEIGHT {
-            for x in 0..WIDTH {
-                if self.character.y == y && self.character.x == x {
-                    print!("@ ");
-                } else {
-                    print!("{} ", self.points[y][x]);
-                }
-            }
-            println!();
-        }
+        print!("{}", self);
     }
 }
 
-impl PartialEq for State {
-    fn eq(&self, other: &Self) -> bool {
-        self.evaluated_score == other.evaluated_score
+// clone_from で actions や points のバッファを使い回せるように手で実装する (StatePool 用)
+impl Clone for State {
+    fn clone(&self) -> Self {
+        State {
+            first_action: self.first_action,
+            actions: self.actions.clone(),
+            character: self.character,
+            points: self.points.clone(),
+            turn: self.turn,
+            end_turn: self.end_turn,
+            game_score: self.game_score,
+            evaluated_score: self.evaluated_score,
+            regrowing: self.regrowing.clone(),
+            rules: self.rules.clone(),
+        }
     }
-}
-
-impl Eq for State {}
 
-impl PartialOrd for State {
-    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some(self.evaluated_score.cmp(&other.evaluated_score))
+    fn clone_from(&mut self, source: &Self) {
+        self.first_action = source.first_action;
+        self.actions.clone_from(&source.actions);
+        self.character = source.character;
+        self.points.clone_from(&source.points);
+        self.turn = source.turn;
+        self.end_turn = source.end_turn;
+        self.game_score = source.game_score;
+        self.evaluated_score = source.evaluated_score;
+        self.regrowing.clone_from(&source.regrowing);
+        self.rules.clone_from(&source.rules);
     }
 }
 
-impl Ord for State {
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.evaluated_score.cmp(&other.evaluated_score)
+impl std::fmt::Display for State {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# turn {}", self.turn)?;
+        writeln!(f, "score {}", self.evaluated_score)?;
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                if self.character.y == y && self.character.x == x {
+                    // 床以外のマスにいるときは地形も書いておく
+                    match self.rules.tiles[y][x] {
+                        Tile::OneWay(direction) => write!(f, "@{} ", direction.arrow().unwrap())?,
+                        Tile::Teleporter { label, .. } => write!(f, "@{} ", label)?,
+                        _ => write!(f, "@ ")?,
+                    }
+                    continue;
+                }
+                match self.rules.tiles[y][x] {
+                    Tile::Floor => write!(f, "{} ", self.points[y][x])?,
+                    Tile::Wall => write!(f, "# ")?,
+                    Tile::OneWay(direction) => write!(f, "{} ", direction.arrow().unwrap())?,
+                    Tile::Teleporter { label, .. } => write!(f, "{} ", label)?,
+                }
+            }
+            writeln!(f)?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::generator::strategy::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_advance() {
         let character = Pos::new(1, 1);
         let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
         let mut maze_state = State::new(character, points);
-        let action = 0;
+        let action = Action::Right;
         maze_state.advance(action);
-        let action = 1;
+        let action = Action::Down;
         maze_state.advance(action);
 
         assert_eq!(maze_state.turn, 2);
         assert_eq!(maze_state.character.y, 2);
         assert_eq!(maze_state.character.x, 2);
         assert_eq!(maze_state.game_score, 8);
+        assert_eq!(maze_state.actions, vec![Action::Right, Action::Down]);
     }
 
     #[test]
@@ -139,14 +525,237 @@ mod tests {
         let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
         let state = State::new(character, points);
         let legal_actions = state.legal_actions();
-        let expect = vec![0, 1];
+        let expect = vec![Action::Right, Action::Down];
         assert_eq!(legal_actions, expect);
 
         let character = Pos::new(1, 1);
         let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
         let state = State::new(character, points);
         let legal_actions = state.legal_actions();
-        let expect = vec![0, 1, 2, 3];
+        let expect = vec![Action::Right, Action::Down, Action::Left, Action::Up];
         assert_eq!(legal_actions, expect);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_parse() {
+        let state = State::parse(
+            "
+            4 6 1 3
+            0 @ 2 0
+            7 5 6 6
+            ",
+        )
+        .unwrap();
+        assert_eq!(state.character, Pos::new(1, 1));
+        assert_eq!(
+            state.points.to_rows(),
+            vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]]
+        );
+
+        // Display の出力も読める
+        let same = State::parse(&state.to_string()).unwrap();
+        assert_eq!(same.points, state.points);
+        assert_eq!(same.character, state.character);
+
+        assert!(State::parse("1 2\n3").is_err());
+        assert!(State::parse("1 2\n3 4").is_err());
+        assert!(State::parse("@ a 1").is_err());
+        assert!(State::parse("@ x 1").is_err());
+        assert!(State::parse("@# 1").is_err());
+    }
+
+    #[test]
+    fn test_parse_round_trip_on_special_tiles() {
+        // テレポートするとキャラクターはペアのパッドの上にいる
+        let mut state = State::parse(
+            "
+            @ a 0
+            0 0 0
+            9 0 a
+            ",
+        )
+        .unwrap();
+        state.advance(Action::Right);
+        assert_eq!(state.character, Pos::new(2, 2));
+        let same = State::parse(&state.to_string()).unwrap();
+        assert_eq!(same.character, state.character);
+        assert_eq!(same.rules.tiles, state.rules.tiles);
+        assert_eq!(same.points, state.points);
+
+        // 一方通行のマスの上から始まる盤面
+        let state = State::parse("@> 1\n3 4").unwrap();
+        assert_eq!(state.tile(state.character), Tile::OneWay(Action::Right));
+        assert_eq!(
+            State::parse(&state.to_string()).unwrap().rules.tiles,
+            state.rules.tiles
+        );
+    }
+
+    #[test]
+    fn test_walls_and_one_way() {
+        let state = State::parse(
+            "
+            1 # 3
+            @ > 5
+            ^ 9 9
+            ",
+        )
+        .unwrap();
+        // 右(>)には入れる、下は^なので入れない
+        assert_eq!(state.legal_actions(), vec![Action::Right, Action::Up]);
+
+        let mut state = state;
+        state.advance(Action::Up);
+        // 右は壁
+        assert_eq!(state.legal_actions(), vec![Action::Down]);
+        assert_eq!(state.to_string().lines().nth(2), Some("@ # 3 "));
+    }
+
+    #[test]
+    fn test_teleporter() {
+        let mut state = State::parse(
+            "
+            @ a 0
+            0 0 0
+            9 0 a
+            ",
+        )
+        .unwrap();
+        state.advance(Action::Right);
+        assert_eq!(state.character, Pos::new(2, 2));
+
+        // 着地した側のパッドからは飛ばずに普通に移動できる
+        state.advance(Action::Left);
+        assert_eq!(state.character, Pos::new(2, 1));
+        state.advance(Action::Left);
+        assert_eq!(state.game_score, 9);
+        assert_eq!(state.turn, 3);
+    }
+
+    #[test]
+    fn test_stay_and_custom_actions() {
+        let character = Pos::new(0, 0);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        // 桂馬飛び (下に2、右に1)
+        let state = State::new(character, points)
+            .with_actions(vec![Action::Right, Action::Stay])
+            .with_custom_action(2, 1);
+        assert_eq!(
+            state.legal_actions(),
+            vec![Action::Right, Action::Stay, Action::Custom(0)]
+        );
+
+        let mut state = state;
+        state.advance(Action::Stay);
+        assert_eq!(state.character, Pos::new(0, 0));
+        assert_eq!(state.turn, 1);
+        state.advance(Action::Custom(0));
+        assert_eq!(state.character, Pos::new(2, 1));
+        assert_eq!(state.game_score, 5);
+        // 盤面の外に出る桂馬飛びは選べない
+        assert_eq!(state.legal_actions(), vec![Action::Right, Action::Stay]);
+
+        // 移動量を登録していない Custom も選べない
+        let state = state.with_actions(vec![Action::Right, Action::Custom(3)]);
+        assert_eq!(state.legal_actions(), vec![Action::Right]);
+    }
+
+    #[test]
+    #[should_panic(expected = "must point up, down, left or right")]
+    fn test_one_way_stay_is_rejected() {
+        State::parse("@ 1")
+            .unwrap()
+            .with_tiles(vec![vec![Tile::Floor, Tile::OneWay(Action::Stay)]]);
+    }
+
+    #[test]
+    fn test_regrowth() {
+        let state = State::parse("@ 5")
+            .unwrap()
+            .with_regrowth(2)
+            .with_end_turn(6);
+        let mut state = state.with_actions(vec![Action::Right, Action::Left, Action::Stay]);
+
+        state.advance(Action::Right);
+        assert_eq!(state.game_score, 5);
+        assert_eq!(state.points[0][1], 0);
+        state.advance(Action::Stay);
+        assert_eq!(state.points[0][1], 0);
+        // 取ってから2ターン経つと元に戻る
+        state.advance(Action::Stay);
+        assert_eq!(state.points[0][1], 5);
+        state.advance(Action::Stay);
+        assert_eq!(state.game_score, 10);
+        assert_eq!(state.regrowing.len(), 1);
+    }
+
+    #[test]
+    fn test_reward_schedule() {
+        let mut state = State::parse("@ 4 6")
+            .unwrap()
+            .with_reward(RewardSchedule::Table(vec![1.0, 0.5]));
+        state.advance(Action::Right);
+        state.advance(Action::Right);
+        assert_eq!(state.game_score, 4 + 3);
+
+        assert_eq!(RewardSchedule::Decay(0.5).reward(8, 3), 1);
+        assert_eq!(RewardSchedule::Table(vec![2.0, 3.0]).reward(2, 10), 6);
+        assert_eq!(RewardSchedule::Constant.reward(7, 100), 7);
+    }
+
+    #[test]
+    fn test_spawn_outcomes() {
+        let state = State::parse("@ 0 #\n3 0 a\n0 0 a").unwrap();
+        assert_eq!(state.spawn_outcomes().len(), 1);
+
+        let state = state.with_spawn(Spawn {
+            probability: 0.6,
+            value: 5,
+        });
+        let outcomes = state.spawn_outcomes();
+        // 湧かない場合 + 空いている床4マス
+        assert_eq!(outcomes.len(), 5);
+        let total = outcomes.iter().map(|(p, _)| p).sum::<f64>();
+        assert!((total - 1.0).abs() < 1e-9);
+        assert!((outcomes[1].0 - 0.15).abs() < 1e-9);
+        assert_eq!(outcomes[1].1.points[0][1], 5);
+
+        let mut rng = XorShift::new(0);
+        let mut spawned = 0;
+        for _ in 0..1000 {
+            let mut next_state = state.clone();
+            next_state.spawn_random(&mut rng);
+            if next_state.points.iter().sum::<usize>() > 3 {
+                spawned += 1;
+            }
+        }
+        assert!((500..700).contains(&spawned));
+    }
+
+    proptest! {
+        #[test]
+        fn prop_advance_invariants(
+            state in arb_state(true),
+            choices in proptest::collection::vec(any::<usize>(), 0..10),
+        ) {
+            let total = state.points.iter().sum::<usize>();
+            let mut state = state;
+            for choice in choices {
+                let legal_actions = state.legal_actions();
+                if legal_actions.is_empty() {
+                    break;
+                }
+                let action = legal_actions[choice % legal_actions.len()];
+                let turn = state.turn;
+                state.advance(action);
+
+                prop_assert!(state.character.y < state.height());
+                prop_assert!(state.character.x < state.width());
+                prop_assert_ne!(state.tile(state.character), Tile::Wall);
+                prop_assert_eq!(state.turn, turn + 1);
+                // 取った点の合計 = 盤面から減った点
+                prop_assert_eq!(state.game_score + state.points.iter().sum::<usize>(), total);
+            }
+        }
+    }
+}
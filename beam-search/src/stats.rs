@@ -0,0 +1,253 @@
+use crate::observer::*;
+use crate::search_state::*;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+// 1層分の統計
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerStats {
+    pub pass: usize,
+    pub depth: usize,
+    pub expanded: usize,
+    pub generated: usize,
+    pub duplicate_children: usize,
+    pub beam_size: usize,
+    // to_json と同じく、serde でもマイクロ秒の整数 elapsed_us として読み書きする
+    #[cfg_attr(feature = "serde", serde(rename = "elapsed_us", with = "elapsed_us"))]
+    pub elapsed: Duration,
+    pub best_score: usize,
+    pub scores: Vec<usize>,
+}
+
+#[cfg(feature = "serde")]
+mod elapsed_us {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(elapsed: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(elapsed.as_micros() as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_micros)
+    }
+}
+
+impl LayerStats {
+    pub fn min_score(&self) -> Option<usize> {
+        self.scores.iter().copied().min()
+    }
+
+    pub fn max_score(&self) -> Option<usize> {
+        self.scores.iter().copied().max()
+    }
+
+    pub fn mean_score(&self) -> Option<f64> {
+        if self.scores.is_empty() {
+            return None;
+        }
+        Some(self.scores.iter().sum::<usize>() as f64 / self.scores.len() as f64)
+    }
+}
+
+// beam_search / chokudai_search の observer として渡して統計を集める
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchStats {
+    pub layers: Vec<LayerStats>,
+}
+
+impl SearchStats {
+    pub fn new() -> Self {
+        SearchStats { layers: vec![] }
+    }
+
+    pub fn total_generated(&self) -> usize {
+        self.layers.iter().map(|layer| layer.generated).sum()
+    }
+
+    pub fn total_duplicate_children(&self) -> usize {
+        self.layers
+            .iter()
+            .map(|layer| layer.duplicate_children)
+            .sum()
+    }
+
+    pub fn total_elapsed(&self) -> Duration {
+        self.layers.iter().map(|layer| layer.elapsed).sum()
+    }
+
+    // depth ごとの生成ノード数 (chokudaiサーチは全周の合計)
+    pub fn generated_per_depth(&self) -> Vec<usize> {
+        let max_depth = self.layers.iter().map(|layer| layer.depth).max();
+        let mut generated = vec![0; max_depth.map_or(0, |d| d + 1)];
+        for layer in self.layers.iter() {
+            generated[layer.depth] += layer.generated;
+        }
+        generated
+    }
+
+    // スコア分布は min/max/mean に要約する。生の値が欲しい場合は to_json を使う
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "pass,depth,expanded,generated,duplicate_children,beam_size,elapsed_us,best_score,min_score,max_score,mean_score\n",
+        );
+        for layer in self.layers.iter() {
+            writeln!(
+                csv,
+                "{},{},{},{},{},{},{},{},{},{},{}",
+                layer.pass,
+                layer.depth,
+                layer.expanded,
+                layer.generated,
+                layer.duplicate_children,
+                layer.beam_size,
+                layer.elapsed.as_micros(),
+                layer.best_score,
+                layer.min_score().map_or(String::new(), |s| s.to_string()),
+                layer.max_score().map_or(String::new(), |s| s.to_string()),
+                layer.mean_score().map_or(String::new(), |s| s.to_string()),
+            )
+            .unwrap();
+        }
+        csv
+    }
+
+    // serde を使わずに書き出す。serde feature の Serialize と同じ形になる
+    pub fn to_json(&self) -> String {
+        let layers = self
+            .layers
+            .iter()
+            .map(|layer| {
+                let scores = layer
+                    .scores
+                    .iter()
+                    .map(|s| s.to_string())
+                    .collect::<Vec<String>>()
+                    .join(",");
+                format!(
+                    r#"{{"pass":{},"depth":{},"expanded":{},"generated":{},"duplicate_children":{},"beam_size":{},"elapsed_us":{},"best_score":{},"scores":[{}]}}"#,
+                    layer.pass,
+                    layer.depth,
+                    layer.expanded,
+                    layer.generated,
+                    layer.duplicate_children,
+                    layer.beam_size,
+                    layer.elapsed.as_micros(),
+                    layer.best_score,
+                    scores
+                )
+            })
+            .collect::<Vec<String>>();
+        format!(r#"{{"layers":[{}]}}"#, layers.join(","))
+    }
+}
+
+impl<S: SearchState> SearchObserver<S> for SearchStats {
+    fn on_layer(&mut self, info: &LayerInfo<S>) {
+        self.layers.push(LayerStats {
+            pass: info.pass,
+            depth: info.depth,
+            expanded: info.expanded,
+            generated: info.generated,
+            duplicate_children: info.duplicate_children,
+            beam_size: info.beam_size,
+            elapsed: info.elapsed,
+            best_score: info.best_score(),
+            scores: info.scores.to_vec(),
+        });
+    }
+
+    fn detailed(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beam_search::*;
+    use crate::chokudai_search::*;
+    use crate::state::*;
+
+    #[test]
+    fn test_beam_search_stats() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let mut stats = SearchStats::new();
+        beam_search_with_observer(&state, 2, 4, &mut stats);
+
+        assert_eq!(stats.layers.len(), 4);
+        assert_eq!(stats.generated_per_depth(), vec![0, 4, 6, 5, 6]);
+        assert_eq!(stats.total_generated(), 21);
+        assert_eq!(stats.layers[0].scores.len(), 4);
+        assert_eq!(stats.layers[0].max_score(), Some(6));
+        assert_eq!(stats.layers[0].min_score(), Some(0));
+    }
+
+    #[test]
+    fn test_chokudai_search_stats() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let mut stats = SearchStats::new();
+        chokudai_search_with_observer(&state, 1, 2, 2, &mut stats);
+
+        // 1周目は depth 1, 2、2周目は初期状態を使い切っているので depth 2 のみ
+        let layers = stats
+            .layers
+            .iter()
+            .map(|layer| (layer.pass, layer.depth))
+            .collect::<Vec<(usize, usize)>>();
+        assert_eq!(layers, vec![(0, 1), (0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn test_duplicate_children() {
+        // 点が全部0なので、同じマスに戻る行動列は全て同じ盤面になる
+        let character = Pos::new(1, 1);
+        let points = vec![vec![0; 4]; 3];
+        let state = State::new(character, points);
+        let mut stats = SearchStats::new();
+        beam_search_with_observer(&state, 4, 2, &mut stats);
+
+        assert_eq!(stats.layers[1].generated, 13);
+        assert_eq!(stats.layers[1].duplicate_children, 7);
+    }
+
+    #[test]
+    fn test_to_csv_and_json() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let mut stats = SearchStats::new();
+        beam_search_with_observer(&state, 2, 4, &mut stats);
+
+        let csv = stats.to_csv();
+        assert_eq!(csv.lines().count(), 5);
+        assert!(csv.lines().nth(1).unwrap().starts_with("0,1,1,4,0,4,"));
+
+        let json = stats.to_json();
+        assert!(json.starts_with(r#"{"layers":[{"pass":0,"depth":1,"#));
+        assert_eq!(json.matches("\"scores\"").count(), 4);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_to_json_matches_serde() {
+        let character = Pos::new(1, 1);
+        let points = vec![vec![4, 6, 1, 3], vec![0, 0, 2, 0], vec![7, 5, 6, 6]];
+        let state = State::new(character, points);
+        let mut stats = SearchStats::new();
+        beam_search_with_observer(&state, 2, 4, &mut stats);
+        stats.layers[0].elapsed = Duration::from_micros(1234);
+
+        let json = stats.to_json();
+        assert_eq!(json, serde_json::to_string(&stats).unwrap());
+        let restored: SearchStats = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.layers[0].elapsed, Duration::from_micros(1234));
+        assert_eq!(restored.to_json(), json);
+    }
+}
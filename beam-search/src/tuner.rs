@@ -0,0 +1,179 @@
+use crate::action::*;
+use crate::beam_search::*;
+use crate::chokudai_search::*;
+use crate::driver::*;
+use crate::state::*;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    BeamSearch,
+    ChokudaiSearch,
+}
+
+// beam_number は chokudaiサーチでのみ使う
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    pub beam_width: usize,
+    pub beam_depth: usize,
+    pub beam_number: usize,
+}
+
+// グリッドサーチする候補値
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchSpace {
+    pub beam_widths: Vec<usize>,
+    pub beam_depths: Vec<usize>,
+    pub beam_numbers: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TuneResult {
+    pub algorithm: Algorithm,
+    pub config: Config,
+    pub average_score: f64,
+    pub max_elapsed: Duration,
+    // どれかのインスタンスで制限時間を超えたら失格
+    pub within_budget: bool,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TuneReport {
+    pub results: Vec<TuneResult>,
+}
+
+impl TuneReport {
+    // 制限時間内に収まった設定の中で平均スコアが最も高いもの
+    pub fn best(&self, algorithm: Algorithm) -> Option<&TuneResult> {
+        self.results
+            .iter()
+            .filter(|result| result.algorithm == algorithm && result.within_budget)
+            .max_by(|a, b| a.average_score.total_cmp(&b.average_score))
+    }
+}
+
+fn search(state: &State, algorithm: Algorithm, config: &Config) -> Option<Action> {
+    match algorithm {
+        Algorithm::BeamSearch => beam_search(state, config.beam_width, config.beam_depth),
+        Algorithm::ChokudaiSearch => chokudai_search(
+            state,
+            config.beam_width,
+            config.beam_depth,
+            config.beam_number,
+        ),
+    }
+}
+
+fn evaluate(
+    instances: &[State],
+    time_budget: Duration,
+    algorithm: Algorithm,
+    config: Config,
+) -> TuneResult {
+    let mut total_score = 0;
+    let mut max_elapsed = Duration::ZERO;
+    let mut within_budget = true;
+    for instance in instances {
+        let start = Instant::now();
+        total_score += play(instance, |state| search(state, algorithm, &config)).game_score;
+        let elapsed = start.elapsed();
+        max_elapsed = max_elapsed.max(elapsed);
+        if elapsed > time_budget {
+            // 残りのインスタンスを回しても無駄なので打ち切る
+            within_budget = false;
+            break;
+        }
+    }
+    TuneResult {
+        algorithm,
+        config,
+        average_score: total_score as f64 / instances.len().max(1) as f64,
+        max_elapsed,
+        within_budget,
+    }
+}
+
+// 各インスタンスについて1ゲーム通して遊び、平均スコアで設定を比較する
+// time_budget は1インスタンス(1ゲーム)あたりの制限時間
+pub fn tune(instances: &[State], time_budget: Duration, space: &SearchSpace) -> TuneReport {
+    let mut results = vec![];
+    for &beam_width in space.beam_widths.iter() {
+        for &beam_depth in space.beam_depths.iter() {
+            let config = Config {
+                beam_width,
+                beam_depth,
+                beam_number: 0,
+            };
+            results.push(evaluate(
+                instances,
+                time_budget,
+                Algorithm::BeamSearch,
+                config,
+            ));
+
+            for &beam_number in space.beam_numbers.iter() {
+                let config = Config {
+                    beam_width,
+                    beam_depth,
+                    beam_number,
+                };
+                results.push(evaluate(
+                    instances,
+                    time_budget,
+                    Algorithm::ChokudaiSearch,
+                    config,
+                ));
+            }
+        }
+    }
+    TuneReport { results }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::*;
+
+    #[test]
+    fn test_tune() {
+        let instances = generate_instances(4, 4, 6, 0, 3);
+        let space = SearchSpace {
+            beam_widths: vec![1, 4],
+            beam_depths: vec![1, 3],
+            beam_numbers: vec![1, 2],
+        };
+        let report = tune(&instances, Duration::from_secs(10), &space);
+
+        // beam: 2 * 2, chokudai: 2 * 2 * 2
+        assert_eq!(report.results.len(), 12);
+
+        for algorithm in [Algorithm::BeamSearch, Algorithm::ChokudaiSearch] {
+            let best = report.best(algorithm).unwrap();
+            assert_eq!(best.algorithm, algorithm);
+            assert!(report
+                .results
+                .iter()
+                .filter(|result| result.algorithm == algorithm)
+                .all(|result| result.average_score <= best.average_score));
+        }
+    }
+
+    #[test]
+    fn test_tune_over_budget() {
+        let instances = generate_instances(4, 4, 6, 0, 3);
+        let space = SearchSpace {
+            beam_widths: vec![4],
+            beam_depths: vec![3],
+            beam_numbers: vec![2],
+        };
+        let report = tune(&instances, Duration::ZERO, &space);
+
+        assert!(report.results.iter().all(|result| !result.within_budget));
+        assert!(report.best(Algorithm::BeamSearch).is_none());
+    }
+}
@@ -70,6 +70,7 @@ pub fn copy_files_with_dir_name(path_vec: Vec<PathBuf>, dist: &Path) -> io::Resu
 }
 
 #[cfg(test)]
+#[allow(clippy::useless_vec, clippy::assertions_on_constants)]
 mod tests {
     use std::collections::BTreeSet;
 
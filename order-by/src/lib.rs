@@ -0,0 +1,98 @@
+// #[derive(OrderBy)] で、指定したフィールドで比べる PartialEq / Eq / PartialOrd / Ord を実装する
+//
+//   #[derive(OrderBy)]
+//   #[order_by(dist, asc)]
+//   #[order_by(score, desc)]
+//   struct Point { id: usize, dist: usize, score: usize }
+//
+// asc / desc は trait_sort::lexicographic_ord! と同じく BinaryHeap(優先度付きキュー)から pop() される順。
+//   asc:  値が小さいほうを先に pop() する (Ordering を逆にする)
+//   desc: 値が大きいほうを先に pop() する (そのままの Ordering)
+// #[order_by] を複数書くと、上から順に前のフィールドが同じときだけ次のフィールドを比べる。
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{parse_macro_input, DeriveInput, Ident, Member, Token};
+
+struct OrderBy {
+    field: Member,
+    descending: bool,
+}
+
+impl Parse for OrderBy {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let field = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let direction: Ident = input.parse()?;
+        let descending = match direction.to_string().as_str() {
+            "asc" => false,
+            "desc" => true,
+            _ => {
+                return Err(syn::Error::new(
+                    direction.span(),
+                    "expected `asc` or `desc`",
+                ))
+            }
+        };
+        Ok(OrderBy { field, descending })
+    }
+}
+
+#[proc_macro_derive(OrderBy, attributes(order_by))]
+pub fn derive_order_by(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let orders = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("order_by"))
+        .map(|attr| attr.parse_args::<OrderBy>())
+        .collect::<syn::Result<Vec<OrderBy>>>()?;
+    if orders.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "#[derive(OrderBy)] needs at least one #[order_by(field, asc|desc)]",
+        ));
+    }
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let fields = orders.iter().map(|order| &order.field).collect::<Vec<_>>();
+    let comparisons = orders.iter().map(|order| {
+        let field = &order.field;
+        if order.descending {
+            quote!(::std::cmp::Ord::cmp(&self.#field, &other.#field))
+        } else {
+            quote!(::std::cmp::Ord::cmp(&other.#field, &self.#field))
+        }
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::std::cmp::PartialEq for #name #ty_generics #where_clause {
+            fn eq(&self, other: &Self) -> bool {
+                #(self.#fields == other.#fields)&&*
+            }
+        }
+
+        impl #impl_generics ::std::cmp::Eq for #name #ty_generics #where_clause {}
+
+        impl #impl_generics ::std::cmp::PartialOrd for #name #ty_generics #where_clause {
+            fn partial_cmp(&self, other: &Self) -> ::std::option::Option<::std::cmp::Ordering> {
+                ::std::option::Option::Some(::std::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        impl #impl_generics ::std::cmp::Ord for #name #ty_generics #where_clause {
+            fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+                ::std::cmp::Ordering::Equal
+                    #(.then_with(|| #comparisons))*
+            }
+        }
+    })
+}
@@ -0,0 +1,77 @@
+use order_by::OrderBy;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+#[derive(Debug, OrderBy)]
+#[order_by(score, desc)]
+struct Node {
+    id: usize,
+    score: i32,
+}
+
+#[derive(OrderBy)]
+#[order_by(dist, asc)]
+#[order_by(score, desc)]
+struct Point {
+    id: usize,
+    dist: usize,
+    score: usize,
+}
+
+#[derive(OrderBy)]
+#[order_by(1, asc)]
+struct Wrapper<T>(T, u32);
+
+#[test]
+fn test_single_field() {
+    let a = Node { id: 0, score: 10 };
+    let b = Node { id: 1, score: 10 };
+    let c = Node { id: 2, score: -20 };
+    // id は比べない
+    assert_ne!(a.id, b.id);
+    assert_eq!(a, b);
+    assert_eq!(a.cmp(&b), Ordering::Equal);
+    assert!(a > c);
+    assert_eq!(c.partial_cmp(&a), Some(Ordering::Less));
+}
+
+#[test]
+fn test_lexicographic() {
+    let points = [
+        Point {
+            id: 0,
+            dist: 2,
+            score: 10,
+        },
+        Point {
+            id: 1,
+            dist: 0,
+            score: 30,
+        },
+        Point {
+            id: 2,
+            dist: 2,
+            score: 20,
+        },
+        Point {
+            id: 3,
+            dist: 30,
+            score: 0,
+        },
+    ];
+    let mut q = BinaryHeap::from(points);
+    let mut ans = vec![];
+    while let Some(point) = q.pop() {
+        ans.push(point.id);
+    }
+    assert_eq!(ans, vec![1, 2, 0, 3]);
+}
+
+#[test]
+fn test_generic_tuple_struct() {
+    // T は比べないので Ord でなくてよい
+    let mut wrappers = [Wrapper(0.5, 3), Wrapper(1.5, 1), Wrapper(2.5, 2)];
+    wrappers.sort();
+    let sorted = wrappers.iter().map(|w| w.1).collect::<Vec<u32>>();
+    assert_eq!(sorted, vec![3, 2, 1]);
+}
@@ -0,0 +1,322 @@
+use crate::one_comparison::Node;
+use crate::two_comparison::Point;
+
+// IndexedPriorityQueue に入れる要素は id で区別する
+pub trait HasId {
+    fn id(&self) -> usize;
+}
+
+impl HasId for Point {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl HasId for Node {
+    fn id(&self) -> usize {
+        self.id
+    }
+}
+
+// BinaryHeap と同じく Ord が大きいものから pop() する優先度付きキュー。
+// 同じ id の要素は1つしか持たず、id を指定して入れ替え・削除ができる。
+// BinaryHeap で Dijkstra 法を書くと同じ頂点を何度も push() して古いものを読み飛ばすことになるが、
+// こちらは decrease_key() で入っている要素を更新する。
+pub struct IndexedPriorityQueue<T> {
+    heap: Vec<T>,
+    // pos[id] = heap 上の位置
+    pos: Vec<Option<usize>>,
+}
+
+impl<T: Ord + HasId> IndexedPriorityQueue<T> {
+    pub fn new() -> Self {
+        IndexedPriorityQueue {
+            heap: vec![],
+            pos: vec![],
+        }
+    }
+
+    // id が 0..n に収まるとわかっているときに使う
+    pub fn with_capacity(n: usize) -> Self {
+        IndexedPriorityQueue {
+            heap: Vec::with_capacity(n),
+            pos: vec![None; n],
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn contains(&self, id: usize) -> bool {
+        self.position(id).is_some()
+    }
+
+    pub fn get(&self, id: usize) -> Option<&T> {
+        self.position(id).map(|i| &self.heap[i])
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.first()
+    }
+
+    // 同じ id の要素が入っていたら置き換えて、古いほうを返す
+    pub fn push(&mut self, item: T) -> Option<T> {
+        match self.position(item.id()) {
+            Some(i) => Some(self.replace_at(i, item)),
+            None => {
+                let id = item.id();
+                if self.pos.len() <= id {
+                    self.pos.resize(id + 1, None);
+                }
+                self.heap.push(item);
+                let i = self.heap.len() - 1;
+                self.pos[id] = Some(i);
+                self.sift_up(i);
+                None
+            }
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        if self.heap.is_empty() {
+            return None;
+        }
+        Some(self.remove_at(0))
+    }
+
+    pub fn remove(&mut self, id: usize) -> Option<T> {
+        self.position(id).map(|i| self.remove_at(i))
+    }
+
+    // 入っている要素を、より先に pop() される (Ord が大きい) 要素で置き換える。
+    // Point なら dist が小さくなったとき。置き換えた古い要素を返す
+    pub fn decrease_key(&mut self, item: T) -> T {
+        let i = self.position(item.id()).expect("id is not in the queue");
+        assert!(
+            item >= self.heap[i],
+            "decrease_key must not lower the priority"
+        );
+        let old = std::mem::replace(&mut self.heap[i], item);
+        self.sift_up(i);
+        old
+    }
+
+    // decrease_key の逆。より後に pop() される (Ord が小さい) 要素で置き換える
+    pub fn increase_key(&mut self, item: T) -> T {
+        let i = self.position(item.id()).expect("id is not in the queue");
+        assert!(
+            item <= self.heap[i],
+            "increase_key must not raise the priority"
+        );
+        let old = std::mem::replace(&mut self.heap[i], item);
+        self.sift_down(i);
+        old
+    }
+
+    pub fn clear(&mut self) {
+        for item in self.heap.drain(..) {
+            self.pos[item.id()] = None;
+        }
+    }
+
+    fn position(&self, id: usize) -> Option<usize> {
+        self.pos.get(id).copied().flatten()
+    }
+
+    fn replace_at(&mut self, i: usize, item: T) -> T {
+        let old = std::mem::replace(&mut self.heap[i], item);
+        let i = self.sift_up(i);
+        self.sift_down(i);
+        old
+    }
+
+    fn remove_at(&mut self, i: usize) -> T {
+        let last = self.heap.len() - 1;
+        self.swap(i, last);
+        let item = self.heap.pop().unwrap();
+        self.pos[item.id()] = None;
+        if i < self.heap.len() {
+            let i = self.sift_up(i);
+            self.sift_down(i);
+        }
+        item
+    }
+
+    fn swap(&mut self, i: usize, j: usize) {
+        self.heap.swap(i, j);
+        self.pos[self.heap[i].id()] = Some(i);
+        self.pos[self.heap[j].id()] = Some(j);
+    }
+
+    fn sift_up(&mut self, mut i: usize) -> usize {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.heap[i] <= self.heap[parent] {
+                break;
+            }
+            self.swap(i, parent);
+            i = parent;
+        }
+        i
+    }
+
+    fn sift_down(&mut self, mut i: usize) -> usize {
+        loop {
+            let (left, right) = (2 * i + 1, 2 * i + 2);
+            let mut largest = i;
+            if left < self.heap.len() && self.heap[left] > self.heap[largest] {
+                largest = left;
+            }
+            if right < self.heap.len() && self.heap[right] > self.heap[largest] {
+                largest = right;
+            }
+            if largest == i {
+                return i;
+            }
+            self.swap(i, largest);
+            i = largest;
+        }
+    }
+}
+
+impl<T: Ord + HasId> Default for IndexedPriorityQueue<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BinaryHeap;
+
+    use super::*;
+
+    // 疑似乱数で作った有向グラフ。graph[from] = [(to, cost)]
+    fn random_graph(n: usize, m: usize, seed: u64) -> Vec<Vec<(usize, usize)>> {
+        let mut x = seed;
+        let mut next = || {
+            x ^= x << 13;
+            x ^= x >> 7;
+            x ^= x << 17;
+            x
+        };
+        let mut graph = vec![vec![]; n];
+        for _ in 0..m {
+            let from = next() as usize % n;
+            let to = next() as usize % n;
+            let cost = next() as usize % 100;
+            graph[from].push((to, cost));
+        }
+        graph
+    }
+
+    // 同じ頂点を何度も push() して、dist が古いものは読み飛ばす
+    fn dijkstra_lazy(graph: &[Vec<(usize, usize)>], start: usize) -> (Vec<usize>, usize) {
+        let mut dist = vec![usize::MAX; graph.len()];
+        let mut pops = 0;
+        let mut q = BinaryHeap::new();
+        dist[start] = 0;
+        q.push(Point::new(start, 0, 0));
+        while let Some(point) = q.pop() {
+            pops += 1;
+            if point.dist > dist[point.id] {
+                continue;
+            }
+            for &(to, cost) in &graph[point.id] {
+                let d = point.dist + cost;
+                if d < dist[to] {
+                    dist[to] = d;
+                    q.push(Point::new(to, d, 0));
+                }
+            }
+        }
+        (dist, pops)
+    }
+
+    fn dijkstra_indexed(graph: &[Vec<(usize, usize)>], start: usize) -> (Vec<usize>, usize) {
+        let mut dist = vec![usize::MAX; graph.len()];
+        let mut pops = 0;
+        let mut q = IndexedPriorityQueue::with_capacity(graph.len());
+        dist[start] = 0;
+        q.push(Point::new(start, 0, 0));
+        while let Some(point) = q.pop() {
+            pops += 1;
+            for &(to, cost) in &graph[point.id] {
+                let d = point.dist + cost;
+                if d < dist[to] {
+                    if q.contains(to) {
+                        q.decrease_key(Point::new(to, d, 0));
+                    } else {
+                        q.push(Point::new(to, d, 0));
+                    }
+                    dist[to] = d;
+                }
+            }
+        }
+        (dist, pops)
+    }
+
+    #[test]
+    fn test_dijkstra() {
+        for seed in 1..=20 {
+            let graph = random_graph(50, 300, seed);
+            let (lazy, lazy_pops) = dijkstra_lazy(&graph, 0);
+            let (indexed, indexed_pops) = dijkstra_indexed(&graph, 0);
+            assert_eq!(lazy, indexed);
+
+            // 到達できる頂点はちょうど1回ずつ pop() される
+            let reachable = indexed.iter().filter(|&&d| d != usize::MAX).count();
+            assert_eq!(indexed_pops, reachable);
+            assert!(lazy_pops >= indexed_pops);
+        }
+    }
+
+    #[test]
+    fn test_nodes() {
+        let mut q = IndexedPriorityQueue::new();
+        q.push(Node::new(0, 10));
+        q.push(Node::new(1, -20));
+        q.push(Node::new(2, 100));
+        q.push(Node::new(5, 50));
+        assert_eq!(q.len(), 4);
+        assert!(q.contains(5));
+        assert!(!q.contains(3));
+        assert!(!q.contains(100));
+
+        // Node は score desc なので score を上げると先に pop() される
+        let old = q.decrease_key(Node::new(1, 200));
+        assert_eq!(old.score, -20);
+        assert_eq!(q.peek().unwrap().id, 1);
+
+        let old = q.increase_key(Node::new(1, 0));
+        assert_eq!(old.score, 200);
+        assert_eq!(q.get(1).unwrap().score, 0);
+
+        assert_eq!(q.remove(2).unwrap().score, 100);
+        assert!(q.remove(2).is_none());
+
+        // 同じ id を push() すると置き換わる
+        assert_eq!(q.push(Node::new(0, -5)).unwrap().score, 10);
+
+        let mut ans = vec![];
+        while let Some(node) = q.pop() {
+            ans.push(node.id);
+        }
+        assert_eq!(ans, vec![5, 1, 0]);
+        assert!(q.is_empty());
+        assert!(!q.contains(5));
+    }
+
+    #[test]
+    #[should_panic(expected = "decrease_key must not lower the priority")]
+    fn test_decrease_key_wrong_direction() {
+        let mut q = IndexedPriorityQueue::new();
+        q.push(Point::new(0, 5, 0));
+        q.decrease_key(Point::new(0, 10, 0));
+    }
+}
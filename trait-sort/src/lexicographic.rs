@@ -0,0 +1,106 @@
+// フィールドを順に比べる PartialEq / Eq / PartialOrd / Ord をまとめて実装するマクロ
+//
+//   lexicographic_ord!(Point; dist asc, score desc);
+//
+// asc / desc は BinaryHeap(優先度付きキュー)から pop() される順。
+//   asc:  値が小さいほうを先に pop() する (Ordering を逆にする)
+//   desc: 値が大きいほうを先に pop() する (そのままの Ordering)
+// 前のフィールドが同じときだけ次のフィールドを比べる。sort() すると pop() とは逆順に並ぶ。
+// PartialEq も並べたフィールドだけで比べるので、cmp が Equal になるときだけ == になる。
+#[macro_export]
+macro_rules! lexicographic_ord {
+    ($ty:ty; $($field:tt $direction:ident),+ $(,)?) => {
+        impl ::std::cmp::PartialEq for $ty {
+            fn eq(&self, other: &Self) -> bool {
+                $(self.$field == other.$field)&&+
+            }
+        }
+
+        impl ::std::cmp::Eq for $ty {}
+
+        impl ::std::cmp::PartialOrd for $ty {
+            fn partial_cmp(&self, other: &Self) -> ::std::option::Option<::std::cmp::Ordering> {
+                ::std::option::Option::Some(::std::cmp::Ord::cmp(self, other))
+            }
+        }
+
+        impl ::std::cmp::Ord for $ty {
+            fn cmp(&self, other: &Self) -> ::std::cmp::Ordering {
+                ::std::cmp::Ordering::Equal
+                    $(.then_with(|| $crate::lexicographic_ord!(@cmp $direction, self.$field, other.$field)))+
+            }
+        }
+    };
+    (@cmp asc, $a:expr, $b:expr) => {
+        ::std::cmp::Ord::cmp(&$b, &$a)
+    };
+    (@cmp desc, $a:expr, $b:expr) => {
+        ::std::cmp::Ord::cmp(&$a, &$b)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cmp::Ordering;
+    use std::collections::BinaryHeap;
+
+    struct Job {
+        id: usize,
+        priority: u8,
+        deadline: u32,
+        cost: i64,
+    }
+
+    // 優先度が高い順、締め切りが近い順、コストが小さい順
+    lexicographic_ord!(Job; priority desc, deadline asc, cost asc);
+
+    fn job(id: usize, priority: u8, deadline: u32, cost: i64) -> Job {
+        Job {
+            id,
+            priority,
+            deadline,
+            cost,
+        }
+    }
+
+    #[test]
+    fn test_lexicographic_ord() {
+        let jobs = [
+            job(0, 1, 10, 5),
+            job(1, 2, 30, 5),
+            job(2, 2, 20, 7),
+            job(3, 2, 20, -3),
+            job(4, 1, 10, 5),
+        ];
+        let mut q = BinaryHeap::from(jobs);
+        let mut ans = vec![];
+        while let Some(job) = q.pop() {
+            ans.push(job.id);
+        }
+        // id 0 と 4 は全てのフィールドが同じ
+        assert!(ans == vec![3, 2, 1, 0, 4] || ans == vec![3, 2, 1, 4, 0]);
+    }
+
+    #[test]
+    fn test_consistent_with_eq() {
+        let a = job(0, 1, 10, 5);
+        let b = job(1, 1, 10, 5);
+        let c = job(2, 1, 10, 6);
+        assert!(a == b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert!(a != c);
+        assert_eq!(a.partial_cmp(&c), Some(Ordering::Greater));
+        assert!(a > c);
+    }
+
+    #[test]
+    fn test_tuple_struct() {
+        struct Pair(usize, usize);
+        lexicographic_ord!(Pair; 1 desc, 0 asc);
+
+        let mut pairs = [Pair(3, 1), Pair(1, 2), Pair(2, 2)];
+        pairs.sort();
+        let sorted = pairs.iter().map(|p| (p.0, p.1)).collect::<Vec<_>>();
+        assert_eq!(sorted, vec![(3, 1), (2, 2), (1, 2)]);
+    }
+}
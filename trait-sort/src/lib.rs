@@ -1,2 +1,6 @@
-mod one_comparison;
-mod two_comparison;
+#[macro_use]
+pub mod lexicographic;
+pub mod indexed_queue;
+pub mod one_comparison;
+pub mod stable_queue;
+pub mod two_comparison;
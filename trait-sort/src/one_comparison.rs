@@ -1,6 +1,10 @@
 #![allow(unused)]
-use std::cmp::Ordering;
+use order_by::OrderBy;
 
+// https://doc.rust-lang.org/stable/std/cmp/trait.Ord.html#how-can-i-implement-ord
+// PartialEq / Eq / PartialOrd / Ord を score だけで比べるように derive する
+#[derive(OrderBy)]
+#[order_by(score, desc)]
 pub struct Node {
     pub id: usize,
     parent: Option<usize>,
@@ -19,28 +23,6 @@ impl Node {
     }
 }
 
-// https://doc.rust-lang.org/stable/std/cmp/trait.Ord.html#how-can-i-implement-ord
-impl PartialEq for Node {
-    fn eq(&self, other: &Self) -> bool {
-        self.score == other.score
-    }
-}
-
-impl Eq for Node {}
-// or #[derive(Eq)] to struct
-
-impl PartialOrd for Node {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.score.cmp(&other.score))
-    }
-}
-
-impl Ord for Node {
-    fn cmp(&self, other: &Self) -> Ordering {
-        self.score.cmp(&other.score)
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use std::collections::BinaryHeap;
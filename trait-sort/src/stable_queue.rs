@@ -0,0 +1,197 @@
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+// 同じ優先度の要素をどちらの順に pop() するか
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TieBreak {
+    // push() した順 (先入れ先出し)
+    Fifo,
+    // push() した逆順 (後入れ先出し)
+    Lifo,
+}
+
+// BinaryHeap は Ord が Equal の要素を push() した順に pop() しない。
+// push() するたびに数えた通し番号を一緒に比べて、同じ優先度なら TieBreak の順に取り出す。
+pub struct StablePriorityQueue<T> {
+    heap: BinaryHeap<Entry<T>>,
+    tie_break: TieBreak,
+    counter: u64,
+}
+
+struct Entry<T> {
+    item: T,
+    // Fifo なら先に push() したものほど大きくなるように変換しておく
+    order: u64,
+}
+
+impl<T: Ord> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for Entry<T> {}
+
+impl<T: Ord> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.item
+            .cmp(&other.item)
+            .then_with(|| self.order.cmp(&other.order))
+    }
+}
+
+impl<T: Ord> StablePriorityQueue<T> {
+    pub fn new(tie_break: TieBreak) -> Self {
+        StablePriorityQueue {
+            heap: BinaryHeap::new(),
+            tie_break,
+            counter: 0,
+        }
+    }
+
+    pub fn fifo() -> Self {
+        Self::new(TieBreak::Fifo)
+    }
+
+    pub fn lifo() -> Self {
+        Self::new(TieBreak::Lifo)
+    }
+
+    pub fn push(&mut self, item: T) {
+        let order = match self.tie_break {
+            TieBreak::Fifo => u64::MAX - self.counter,
+            TieBreak::Lifo => self.counter,
+        };
+        self.counter += 1;
+        self.heap.push(Entry { item, order });
+    }
+
+    pub fn pop(&mut self) -> Option<T> {
+        self.heap.pop().map(|entry| entry.item)
+    }
+
+    pub fn peek(&self) -> Option<&T> {
+        self.heap.peek().map(|entry| &entry.item)
+    }
+
+    pub fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.heap.is_empty()
+    }
+
+    pub fn tie_break(&self) -> TieBreak {
+        self.tie_break
+    }
+
+    pub fn clear(&mut self) {
+        self.heap.clear();
+        self.counter = 0;
+    }
+}
+
+impl<T: Ord> Default for StablePriorityQueue<T> {
+    fn default() -> Self {
+        Self::fifo()
+    }
+}
+
+impl<T: Ord> Extend<T> for StablePriorityQueue<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        for item in iter {
+            self.push(item);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::one_comparison::Node;
+    use crate::two_comparison::Point;
+
+    fn points() -> [Point; 5] {
+        [
+            Point::new(0, 2, 10),
+            Point::new(1, 0, 30),
+            Point::new(2, 2, 10),
+            Point::new(3, 30, 0),
+            Point::new(4, 5, 100),
+        ]
+    }
+
+    fn nodes() -> [Node; 4] {
+        [
+            Node::new(0, 10),
+            Node::new(1, -20),
+            Node::new(2, 100),
+            Node::new(3, 100),
+        ]
+    }
+
+    #[test]
+    fn test_points() {
+        // BinaryHeap だと id 2 が 0 より先に出る (two_comparison::tests)
+        let mut q = StablePriorityQueue::fifo();
+        q.extend(points());
+        let mut ans = vec![];
+        while let Some(point) = q.pop() {
+            ans.push(point.id);
+        }
+        assert_eq!(ans, vec![1, 0, 2, 4, 3]);
+
+        let mut q = StablePriorityQueue::lifo();
+        q.extend(points());
+        let mut ans = vec![];
+        while let Some(point) = q.pop() {
+            ans.push(point.id);
+        }
+        assert_eq!(ans, vec![1, 2, 0, 4, 3]);
+    }
+
+    #[test]
+    fn test_nodes() {
+        for (tie_break, est) in [
+            (TieBreak::Fifo, vec![2, 3, 0, 1]),
+            (TieBreak::Lifo, vec![3, 2, 0, 1]),
+        ] {
+            let mut q = StablePriorityQueue::new(tie_break);
+            q.extend(nodes());
+            assert_eq!(q.len(), 4);
+            assert_eq!(q.peek().unwrap().id, est[0]);
+
+            let mut ans = vec![];
+            while let Some(node) = q.pop() {
+                ans.push(node.id);
+            }
+            assert_eq!(ans, est);
+            assert!(q.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_interleaved() {
+        // pop() を挟んでも、同じ優先度の中では push() した順
+        let mut q = StablePriorityQueue::fifo();
+        q.push(Node::new(0, 5));
+        q.push(Node::new(1, 5));
+        assert_eq!(q.pop().unwrap().id, 0);
+        q.push(Node::new(2, 5));
+        q.push(Node::new(3, 7));
+        let ans = std::iter::from_fn(|| q.pop())
+            .map(|node| node.id)
+            .collect::<Vec<usize>>();
+        assert_eq!(ans, vec![3, 1, 2]);
+
+        q.clear();
+        assert!(q.pop().is_none());
+    }
+}
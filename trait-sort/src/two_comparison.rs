@@ -1,7 +1,5 @@
 #![allow(unused)]
-use std::cmp::Ordering;
 
-#[derive(Eq)]
 pub struct Point {
     pub id: usize,
     pub dist: usize,
@@ -13,55 +11,13 @@ impl Point {
         Point { id, dist, score }
     }
 }
-impl PartialEq for Point {
-    fn eq(&self, other: &Self) -> bool {
-        (self.dist == other.dist) && (self.score == other.score)
-    }
-}
 
-impl PartialOrd for Point {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        // 最短経路探索等で距離が近いほうを優先的にBinaryHeap(優先度付きキュー)からpop()したいので
-        // distが近いほうをOrdering::Greaterに設定する
-        if self.dist < other.dist {
-            Some(Ordering::Greater)
-        } else if self.dist > other.dist {
-            Some(Ordering::Less)
-        } else {
-            // 仮実装。distが同じならscoreがより高いほうをpop()。
-            if self.score > other.score {
-                Some(Ordering::Greater)
-            } else if self.score < other.score {
-                Some(Ordering::Less)
-            } else {
-                Some(Ordering::Equal)
-            }
-        }
-    }
-}
-
-impl Ord for Point {
-    fn cmp(&self, other: &Self) -> Ordering {
-        // 最短経路探索等で距離が近いほうを優先的にBinaryHeap(優先度付きキュー)からpop()したいので
-        // distが近いほうをOrdering::Greaterに設定する
-        if self.dist < other.dist {
-            Ordering::Greater
-        } else if self.dist > other.dist {
-            Ordering::Less
-        } else {
-            // 仮実装。distが同じならscoreがより高いほうをpop()。
-            if self.score > other.score {
-                Ordering::Greater
-            } else if self.score < other.score {
-                Ordering::Less
-            } else {
-                // distもscoreも同じ場合、BinaryHeap(優先度付きキュー)だとidが大きいものが優先的にpopされる
-                // BinaryHeap(優先度付きキュー)にpush()した順ではないので注意。
-                Ordering::Equal
-            }
-        }
-    }
-}
+// 最短経路探索等で距離が近いほうを優先的にBinaryHeap(優先度付きキュー)からpop()したいので dist asc
+// 仮実装。distが同じならscoreがより高いほうをpop()するので score desc
+// distもscoreも同じ場合、BinaryHeap(優先度付きキュー)にpush()した順ではないので注意。
+// push()した順に取り出したいときは stable_queue::StablePriorityQueue を使う。
+// 同じ頂点を何度もpush()したくないときは indexed_queue::IndexedPriorityQueue を使う。
+lexicographic_ord!(Point; dist asc, score desc);
 
 #[cfg(test)]
 mod tests {